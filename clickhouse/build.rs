@@ -0,0 +1,17 @@
+// 与库中的模块结构一致，`embed_build` 通过 `super::parser` 使用运行时相同的迁移文件解析
+#[allow(dead_code)]
+#[path = "src/clickhouse_migrator"]
+mod clickhouse_migrator {
+    pub mod directives;
+    pub mod embed_build;
+    pub mod lexer;
+    pub mod lint_rule;
+    pub mod parser;
+    pub mod placeholders;
+}
+
+fn main() {
+    // 将 migrations/ 嵌入二进制，供 --embedded 使用
+    clickhouse_migrator::embed_build::generate_embedded_migrations("migrations")
+        .expect("Failed to embed migrations");
+}
@@ -0,0 +1,152 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use crate::config::ClickHouseConfig;
+use crate::database::ClickHouseConnectionManager;
+use super::{MigratorConfig, SimpleMigrator};
+use super::lock::LockConfig;
+use super::cluster::ClusterConfig;
+use super::embedded::{EmbeddedMigrations, MigrationSource};
+use super::code_migration::{ClickHouseMigration, CodeMigration};
+
+/// SimpleMigrator 构建器，通过 `SimpleMigrator::builder` 创建
+pub struct SimpleMigratorBuilder {
+    pub(super) service_name: String,
+    pub(super) connection_manager: Option<ClickHouseConnectionManager>,
+    pub(super) url: String,
+    pub(super) database: String,
+    pub(super) user: String,
+    pub(super) password: String,
+    pub(super) source: MigrationSource,
+    pub(super) code_migrations: Vec<CodeMigration>,
+    pub(super) placeholders: BTreeMap<String, String>,
+    pub(super) history_table: Option<String>,
+    pub(super) config: MigratorConfig,
+    pub(super) lock_config: LockConfig,
+    pub(super) cluster: Option<ClusterConfig>,
+}
+
+impl SimpleMigratorBuilder {
+    pub fn new(service_name: &str) -> Self {
+        Self {
+            service_name: service_name.to_string(),
+            connection_manager: None,
+            url: "http://localhost:8123".to_string(),
+            database: "default".to_string(),
+            user: "default".to_string(),
+            password: String::new(),
+            source: MigrationSource::Directory("migrations".to_string()),
+            code_migrations: Vec::new(),
+            placeholders: BTreeMap::new(),
+            history_table: None,
+            config: MigratorConfig::default(),
+            lock_config: LockConfig::default(),
+            cluster: None,
+        }
+    }
+
+    /// 使用分层配置中的连接参数、服务名、迁移目录、历史表、集群和迁移选项
+    pub fn from_config(config: &ClickHouseConfig) -> Self {
+        let mut builder = Self::new(&config.service_name)
+            .url(&config.url)
+            .database(&config.database)
+            .user(&config.user)
+            .password(&config.password)
+            .migrations_path(&config.migrations_path)
+            .config(config.migrator.clone())
+            .placeholders(&config.placeholders);
+        if let Some(history_table) = &config.history_table {
+            builder = builder.history_table(history_table);
+        }
+        if let Some(cluster) = &config.cluster {
+            builder = builder.cluster(ClusterConfig::new(cluster));
+        }
+        builder
+    }
+
+    /// 使用已有的连接管理器（设置后 url/database/user/password 不再生效）
+    pub fn connection_manager(mut self, connection_manager: ClickHouseConnectionManager) -> Self {
+        self.connection_manager = Some(connection_manager);
+        self
+    }
+
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    pub fn database(mut self, database: &str) -> Self {
+        self.database = database.to_string();
+        self
+    }
+
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = user.to_string();
+        self
+    }
+
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = password.to_string();
+        self
+    }
+
+    /// 运行时从目录读取迁移文件
+    pub fn migrations_path(mut self, migrations_path: &str) -> Self {
+        self.source = MigrationSource::Directory(migrations_path.to_string());
+        self
+    }
+    
+    /// 使用编译期嵌入的迁移文件（`embed_clickhouse_migrations!`），不再读取目录
+    pub fn embedded_migrations(mut self, migrations: EmbeddedMigrations) -> Self {
+        self.source = MigrationSource::Embedded(migrations);
+        self
+    }
+
+    /// 注册 Rust 代码迁移，与迁移文件按版本号统一排序执行
+    pub fn migration(mut self, migration: impl ClickHouseMigration + 'static) -> Self {
+        self.code_migrations.push(CodeMigration::new(migration));
+        self
+    }
+    
+    /// 设置 `${name}` 占位符的值（覆盖内置的 database、service、cluster）
+    pub fn placeholder(mut self, name: &str, value: &str) -> Self {
+        self.placeholders.insert(name.to_string(), value.to_string());
+        self
+    }
+    
+    pub fn placeholders(mut self, placeholders: &BTreeMap<String, String>) -> Self {
+        self.placeholders.extend(placeholders.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self
+    }
+    
+    /// 自定义迁移记录表名（默认 `_migrations_<service>`）
+    pub fn history_table(mut self, table_name: &str) -> Self {
+        self.history_table = Some(table_name.to_string());
+        self
+    }
+
+    pub fn config(mut self, config: MigratorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn lock_config(mut self, lock_config: LockConfig) -> Self {
+        self.lock_config = lock_config;
+        self
+    }
+
+    /// 启用集群模式
+    pub fn cluster(mut self, cluster: ClusterConfig) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// 创建迁移器并确保迁移记录表存在
+    pub async fn build(self) -> Result<SimpleMigrator> {
+        SimpleMigrator::from_builder(self).await
+    }
+
+    /// 创建迁移器但不访问数据库（不创建迁移记录表），只用于 `lint` 等只读取迁移文件的操作
+    pub fn build_offline(self) -> Result<SimpleMigrator> {
+        SimpleMigrator::from_builder_offline(self)
+    }
+}
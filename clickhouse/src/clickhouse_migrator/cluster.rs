@@ -0,0 +1,197 @@
+use anyhow::{Result, Context};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::OnceLock;
+use std::time::Duration;
+use super::lexer::{self, TokenKind};
+
+/// 迁移 SQL 中的集群名占位符
+pub const CLUSTER_MACRO: &str = "{cluster}";
+
+/// 集群（多分片/多副本）部署配置
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    /// `remote_servers` 中定义的集群名
+    pub name: String,
+    /// 迁移记录表在 Keeper 中的路径前缀，不包含 `{shard}`，整个集群共享同一份迁移记录
+    pub replication_path_prefix: String,
+    /// ON CLUSTER 语句等待所有节点完成的最长时间（distributed_ddl_task_timeout）
+    pub ddl_task_timeout: Duration,
+}
+
+impl ClusterConfig {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            replication_path_prefix: "/clickhouse/migrations/{database}".to_string(),
+            ddl_task_timeout: Duration::from_secs(180),
+        }
+    }
+
+    /// 指定表的 ReplicatedMergeTree 引擎参数
+    pub(crate) fn replicated_engine(&self, engine: &str, table_name: &str, extra_args: &str) -> String {
+        let path = format!("{}/{}", self.replication_path_prefix.trim_end_matches('/'), table_name);
+        if extra_args.is_empty() {
+            format!("Replicated{}('{}', '{{replica}}')", engine, path)
+        } else {
+            format!("Replicated{}('{}', '{{replica}}', {})", engine, path, extra_args)
+        }
+    }
+
+    /// 替换 SQL 中的 `{cluster}` 占位符
+    ///
+    /// 只替换代码中的 `{cluster}` 和紧跟在 `ON CLUSTER` 之后的 `'{cluster}'`；注释、其它字符串
+    /// （如 `COMMENT '{cluster}'`、依赖服务器 `{cluster}` 宏的 Keeper 路径）和引用标识符原样保留。
+    pub(crate) fn substitute_macro(&self, sql: &str) -> String {
+        // 无法词法分析的 SQL 原样返回，执行时由服务器报错
+        let Ok(tokens) = lexer::tokenize(sql) else {
+            return sql.to_string();
+        };
+
+        let quoted_macro = format!("'{}'", CLUSTER_MACRO);
+        let mut result = String::with_capacity(sql.len());
+        let mut code_start = None;
+        let mut previous_words: [Option<&str>; 2] = [None, None];
+        for token in &tokens {
+            let is_code = !matches!(
+                token.kind,
+                TokenKind::LineComment | TokenKind::BlockComment
+                    | TokenKind::StringLiteral | TokenKind::QuotedIdentifier | TokenKind::Heredoc
+            );
+            if is_code {
+                code_start.get_or_insert(token.offset);
+            } else {
+                if let Some(start) = code_start.take() {
+                    result.push_str(&sql[start..token.offset].replace(CLUSTER_MACRO, &self.name));
+                }
+                let after_on_cluster = previous_words[0].is_some_and(|w| w.eq_ignore_ascii_case("ON"))
+                    && previous_words[1].is_some_and(|w| w.eq_ignore_ascii_case("CLUSTER"));
+                if token.kind == TokenKind::StringLiteral && after_on_cluster && token.text == quoted_macro {
+                    result.push_str(&format!("'{}'", self.name));
+                } else {
+                    result.push_str(token.text);
+                }
+            }
+            if !token.kind.is_trivia() {
+                previous_words = [previous_words[1], (token.kind == TokenKind::Word).then_some(token.text)];
+            }
+        }
+        if let Some(start) = code_start {
+            result.push_str(&sql[start..].replace(CLUSTER_MACRO, &self.name));
+        }
+        result
+    }
+}
+
+/// ON CLUSTER 语句在单个节点上的执行结果
+#[derive(Debug, Clone, Serialize)]
+pub struct DdlHostResult {
+    pub host: String,
+    pub port: u16,
+    pub status: i64,
+    pub error: String,
+    pub num_hosts_remaining: u64,
+}
+
+/// 单条 ON CLUSTER 语句在所有节点上的执行结果
+#[derive(Debug, Clone, Serialize)]
+pub struct DdlStatementResult {
+    pub version: String,
+    pub statement_index: usize,
+    pub statement: String,
+    pub hosts: Vec<DdlHostResult>,
+}
+
+impl DdlStatementResult {
+    pub fn is_success(&self) -> bool {
+        self.hosts.iter().all(|h| h.status == 0)
+    }
+}
+
+/// 判断语句是否为分布式 DDL
+pub(crate) fn is_on_cluster(sql: &str) -> bool {
+    static ON_CLUSTER: OnceLock<Regex> = OnceLock::new();
+    ON_CLUSTER
+        .get_or_init(|| Regex::new(r"(?i)\bON\s+CLUSTER\b").unwrap())
+        .is_match(sql)
+}
+
+/// 判断语句是否为需要在所有节点执行的 DDL
+pub(crate) fn is_ddl(sql: &str) -> bool {
+    static DDL: OnceLock<Regex> = OnceLock::new();
+    DDL
+        .get_or_init(|| Regex::new(r"(?i)^\s*(CREATE|ALTER|DROP|RENAME|TRUNCATE)\b").unwrap())
+        .is_match(sql)
+}
+
+/// 解析分布式 DDL 返回的 JSONEachRow 结果
+pub(crate) fn parse_host_results(output: &[u8]) -> Result<Vec<DdlHostResult>> {
+    let text = std::str::from_utf8(output)
+        .context("Distributed DDL output is not valid UTF-8")?;
+
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let row: Value = serde_json::from_str(line)
+                .with_context(|| format!("Failed to parse distributed DDL output: {}", line))?;
+            Ok(DdlHostResult {
+                host: json_string(&row, "host"),
+                port: json_u64(&row, "port") as u16,
+                status: json_i64(&row, "status"),
+                error: json_string(&row, "error"),
+                num_hosts_remaining: json_u64(&row, "num_hosts_remaining"),
+            })
+        })
+        .collect()
+}
+
+// JSONEachRow 默认将 64 位整数输出为字符串，这里同时兼容两种形式
+fn json_string(row: &Value, key: &str) -> String {
+    match row.get(key) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn json_i64(row: &Value, key: &str) -> i64 {
+    match row.get(key) {
+        Some(Value::Number(n)) => n.as_i64().unwrap_or_default(),
+        Some(Value::String(s)) => s.parse().unwrap_or_default(),
+        _ => 0,
+    }
+}
+
+fn json_u64(row: &Value, key: &str) -> u64 {
+    json_i64(row, key).max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_cluster_macro_in_code_only() {
+        let cluster = ClusterConfig::new("main");
+        assert_eq!(
+            cluster.substitute_macro("CREATE TABLE t ON CLUSTER {cluster} (id UInt64) ENGINE = Log"),
+            "CREATE TABLE t ON CLUSTER main (id UInt64) ENGINE = Log"
+        );
+        assert_eq!(
+            cluster.substitute_macro("ALTER TABLE t ON CLUSTER '{cluster}' ADD COLUMN x UInt8 COMMENT '{cluster}'"),
+            "ALTER TABLE t ON CLUSTER 'main' ADD COLUMN x UInt8 COMMENT '{cluster}'"
+        );
+        // Keeper 路径中的 {cluster} 由服务器的宏替换
+        let sql = "CREATE TABLE t ON CLUSTER '{cluster}' (id UInt64) \
+                   ENGINE = ReplicatedMergeTree('/clickhouse/{cluster}/tables/{shard}/t', '{replica}') ORDER BY id";
+        assert_eq!(
+            cluster.substitute_macro(sql),
+            sql.replacen("'{cluster}'", "'main'", 1)
+        );
+        assert_eq!(
+            cluster.substitute_macro("-- ON CLUSTER {cluster}\nSELECT `{cluster}` /* {cluster} */"),
+            "-- ON CLUSTER {cluster}\nSELECT `{cluster}` /* {cluster} */"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! 用 Rust 代码编写的迁移：分批回填、需要在 Rust 侧转换的数据复制、依赖 `system.columns` 的条件 DDL 等
+
+use anyhow::Result;
+use clickhouse::Client;
+use std::sync::Arc;
+
+pub use async_trait::async_trait;
+
+/// Rust 代码迁移
+///
+/// 与 SQL 迁移文件按版本号统一排序执行，并记录在同一张历史表中。
+/// 实现时使用本模块重导出的 `#[async_trait]`。
+#[async_trait]
+pub trait ClickHouseMigration: Send + Sync {
+    /// 版本号，不能与迁移文件或其它代码迁移重复
+    fn version(&self) -> u32;
+
+    fn name(&self) -> &str;
+
+    /// 由使用者维护的版本字符串，迁移逻辑变化时需同时修改，校验和由它计算
+    fn checksum_version(&self) -> &str;
+
+    async fn up(&self, client: &Client) -> Result<()>;
+
+    async fn down(&self, client: &Client) -> Result<()>;
+}
+
+/// 已注册的代码迁移
+#[derive(Clone)]
+pub struct CodeMigration(pub(crate) Arc<dyn ClickHouseMigration>);
+
+impl CodeMigration {
+    pub fn new(migration: impl ClickHouseMigration + 'static) -> Self {
+        Self(Arc::new(migration))
+    }
+
+    pub fn migration(&self) -> &dyn ClickHouseMigration {
+        self.0.as_ref()
+    }
+}
+
+impl std::fmt::Debug for CodeMigration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CodeMigration")
+            .field("version", &self.0.version())
+            .field("name", &self.0.name())
+            .field("checksum_version", &self.0.checksum_version())
+            .finish()
+    }
+}
@@ -0,0 +1,213 @@
+//! 迁移文件中的执行指令（`-- +<指令>`），作用于该文件的所有语句：
+//!
+//! - `-- +setting mutations_sync=2`：作为查询设置附加到每条语句
+//! - `-- +timeout 30m`：单条语句的超时时间（同时设置 `max_execution_time`）
+//! - `-- +nosplit`：不按分号分割，整个 Up/Down 部分作为一条语句发送
+//! - `-- +retry 3`：语句因网络错误或超时失败后的重试次数
+//! - `-- +lint-ignore CH001, CH003`：`lint` 不检查该文件的这些规则（该行不计入 SQL 和校验和）
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use super::lint_rule::LintRule;
+
+/// 指令行前缀
+const DIRECTIVE_PREFIX: &str = "-- +";
+/// 只影响静态检查的指令
+const LINT_IGNORE: &str = "lint-ignore";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MigrationDirectives {
+    /// 查询设置，按名称排序
+    pub settings: BTreeMap<String, String>,
+    pub timeout: Option<Duration>,
+    pub no_split: bool,
+    /// 失败后的重试次数（0 表示不重试）
+    pub retries: u32,
+    /// 该文件忽略的静态检查规则
+    pub lint_ignore: BTreeSet<LintRule>,
+}
+
+impl MigrationDirectives {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 解析一行指令；不是指令行时返回 `Ok(false)`
+    ///
+    /// `-- +migrate Up/Down` 由调用方处理，其它未知指令视为错误，避免拼写错误被静默忽略。
+    pub(crate) fn parse_line(&mut self, line: &str) -> Result<bool> {
+        let Some(directive) = line.trim().strip_prefix(DIRECTIVE_PREFIX) else {
+            return Ok(false);
+        };
+        let (name, argument) = match directive.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (directive, ""),
+        };
+
+        match name {
+            "setting" => {
+                let (key, value) = argument.split_once('=')
+                    .map(|(key, value)| (key.trim(), value.trim()))
+                    .filter(|(key, value)| {
+                        !key.is_empty() && !value.is_empty()
+                            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    })
+                    .ok_or_else(|| anyhow!("Invalid directive '{}' (expected: -- +setting name=value)", line.trim()))?;
+                self.settings.insert(key.to_string(), value.to_string());
+            }
+            "timeout" => {
+                self.timeout = Some(parse_duration(argument)
+                    .ok_or_else(|| anyhow!("Invalid directive '{}' (expected e.g. -- +timeout 30m)", line.trim()))?);
+            }
+            "nosplit" => {
+                if !argument.is_empty() {
+                    return Err(anyhow!("Directive '-- +nosplit' takes no argument"));
+                }
+                self.no_split = true;
+            }
+            "retry" => {
+                self.retries = argument.parse()
+                    .map_err(|_| anyhow!("Invalid directive '{}' (expected e.g. -- +retry 3)", line.trim()))?;
+            }
+            LINT_IGNORE => {
+                let rules: Vec<&str> = argument.split([',', ' ']).filter(|rule| !rule.is_empty()).collect();
+                if rules.is_empty() {
+                    return Err(anyhow!("Invalid directive '{}' (expected e.g. -- +lint-ignore CH001)", line.trim()));
+                }
+                for rule in rules {
+                    self.lint_ignore.insert(LintRule::parse(rule)?);
+                }
+            }
+            _ => return Err(anyhow!("Unknown directive: {}", line.trim())),
+        }
+
+        Ok(true)
+    }
+}
+
+/// 是否为 `-- +lint-ignore` 指令行
+pub(crate) fn is_lint_directive(line: &str) -> bool {
+    line.trim().strip_prefix(DIRECTIVE_PREFIX)
+        .is_some_and(|directive| directive.split_whitespace().next() == Some(LINT_IGNORE))
+}
+
+/// 解析 `500ms`、`90s`、`30m`、`2h` 或纯秒数
+pub(crate) fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+
+    match unit.trim() {
+        "ms" => Some(Duration::from_millis(number)),
+        "" | "s" => Some(Duration::from_secs(number)),
+        "m" => Some(Duration::from_secs(number * 60)),
+        "h" => Some(Duration::from_secs(number * 3600)),
+        _ => None,
+    }
+    .filter(|duration| !duration.is_zero())
+}
+
+impl std::fmt::Display for MigrationDirectives {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        for (key, value) in &self.settings {
+            parts.push(format!("{}={}", key, value));
+        }
+        if let Some(timeout) = self.timeout {
+            parts.push(format!("timeout={:?}", timeout));
+        }
+        if self.no_split {
+            parts.push("nosplit".to_string());
+        }
+        if self.retries > 0 {
+            parts.push(format!("retry={}", self.retries));
+        }
+        if !self.lint_ignore.is_empty() {
+            let rules: Vec<&str> = self.lint_ignore.iter().map(|rule| rule.code()).collect();
+            parts.push(format!("lint-ignore={}", rules.join(",")));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(lines: &[&str]) -> Result<MigrationDirectives> {
+        let mut directives = MigrationDirectives::default();
+        for line in lines {
+            directives.parse_line(line)?;
+        }
+        Ok(directives)
+    }
+
+    #[test]
+    fn parses_all_directives() {
+        let directives = parse(&[
+            "-- +setting mutations_sync = 2",
+            "  -- +timeout 30m",
+            "-- +nosplit",
+            "-- +retry 3",
+            "-- +lint-ignore CH001, create-without-if-not-exists",
+        ]).unwrap();
+
+        assert_eq!(directives.settings.get("mutations_sync").map(String::as_str), Some("2"));
+        assert_eq!(directives.timeout, Some(Duration::from_secs(1800)));
+        assert!(directives.no_split);
+        assert_eq!(directives.retries, 3);
+        assert_eq!(
+            directives.lint_ignore,
+            BTreeSet::from([LintRule::VarcharLength, LintRule::CreateWithoutIfNotExists])
+        );
+        assert_eq!(
+            directives.to_string(),
+            "mutations_sync=2, timeout=1800s, nosplit, retry=3, lint-ignore=CH001,CH003"
+        );
+    }
+
+    #[test]
+    fn ignores_non_directive_lines() {
+        let mut directives = MigrationDirectives::default();
+        assert!(!directives.parse_line("-- plain comment").unwrap());
+        assert!(!directives.parse_line("SELECT 1; -- +retry 3").unwrap());
+        assert!(directives.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed_directives() {
+        for line in [
+            "-- +timout 30m",
+            "-- +Retry 3",
+            "-- +timeout 30x",
+            "-- +timeout 0s",
+            "-- +setting max threads=4",
+            "-- +setting mutations_sync",
+            "-- +nosplit yes",
+            "-- +retry many",
+            "-- +lint-ignore",
+            "-- +lint-ignore CH999",
+        ] {
+            assert!(parse(&[line]).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn recognizes_lint_directives() {
+        assert!(is_lint_directive("-- +lint-ignore CH001"));
+        assert!(!is_lint_directive("-- +retry 3"));
+        assert!(!is_lint_directive("-- lint-ignore CH001"));
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("0"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("1d"), None);
+    }
+}
@@ -0,0 +1,592 @@
+//! 结构漂移检测：比较数据库的实际结构（`system.tables`、`system.columns`、
+//! `system.data_skipping_indices`）和提交的结构快照（`schema dump` 生成的 `schema.sql`）
+//!
+//! 直接在生产环境修改表结构后，迁移文件的校验和不会变化，只有和快照比较才能发现。
+
+use anyhow::{anyhow, Result};
+use clickhouse::{Client, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use super::lexer::{self, SqlStatement, Token, TokenKind};
+use super::schema::{self, SchemaObjectKind};
+use super::simple_migrator::MigrationFile;
+
+/// 一项结构差异；expected 来自快照，actual 来自数据库
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SchemaDrift {
+    MissingTable { table: String },
+    ExtraTable { table: String },
+    EngineDiffers { table: String, expected: String, actual: String },
+    MissingColumn { table: String, column: String, expected_type: String },
+    ExtraColumn { table: String, column: String, actual_type: String },
+    TypeChanged { table: String, column: String, expected: String, actual: String },
+    MissingIndex { table: String, index: String },
+    ExtraIndex { table: String, index: String },
+}
+
+impl SchemaDrift {
+    pub fn table(&self) -> &str {
+        match self {
+            SchemaDrift::MissingTable { table }
+            | SchemaDrift::ExtraTable { table }
+            | SchemaDrift::EngineDiffers { table, .. }
+            | SchemaDrift::MissingColumn { table, .. }
+            | SchemaDrift::ExtraColumn { table, .. }
+            | SchemaDrift::TypeChanged { table, .. }
+            | SchemaDrift::MissingIndex { table, .. }
+            | SchemaDrift::ExtraIndex { table, .. } => table,
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaDrift::MissingTable { table } => write!(f, "{}: table is in the snapshot but not in the database", table),
+            SchemaDrift::ExtraTable { table } => write!(f, "{}: table is in the database but not in the snapshot", table),
+            SchemaDrift::EngineDiffers { table, expected, actual } => {
+                write!(f, "{}: engine differs (snapshot: {}, database: {})", table, expected, actual)
+            }
+            SchemaDrift::MissingColumn { table, column, expected_type } => {
+                write!(f, "{}: missing column {} {}", table, column, expected_type)
+            }
+            SchemaDrift::ExtraColumn { table, column, actual_type } => {
+                write!(f, "{}: extra column {} {}", table, column, actual_type)
+            }
+            SchemaDrift::TypeChanged { table, column, expected, actual } => {
+                write!(f, "{}: column {} type changed (snapshot: {}, database: {})", table, column, expected, actual)
+            }
+            SchemaDrift::MissingIndex { table, index } => write!(f, "{}: missing index {}", table, index),
+            SchemaDrift::ExtraIndex { table, index } => write!(f, "{}: extra index {}", table, index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DriftReport {
+    /// 比较使用的快照文件
+    pub snapshot: String,
+    pub drift: Vec<SchemaDrift>,
+}
+
+impl DriftReport {
+    pub fn has_drift(&self) -> bool {
+        !self.drift.is_empty()
+    }
+}
+
+impl std::fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.drift.is_empty() {
+            return write!(f, "No schema drift against {}", self.snapshot);
+        }
+        write!(f, "Schema drift against {} ({} differences):", self.snapshot, self.drift.len())?;
+        for drift in &self.drift {
+            write!(f, "\n  - {}", drift)?;
+        }
+        Ok(())
+    }
+}
+
+/// 存在结构漂移时 `migrate()` 返回的错误
+#[derive(Debug)]
+pub struct DriftError {
+    pub report: DriftReport,
+}
+
+impl std::fmt::Display for DriftError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.report)
+    }
+}
+
+impl std::error::Error for DriftError {}
+
+/// 一个对象的可比较结构
+#[derive(Debug, Default)]
+pub(crate) struct TableSchema {
+    engine: String,
+    /// 按定义顺序的 (列名, 规范化的类型)；视图和字典不比较列
+    columns: Vec<(String, String)>,
+    indices: BTreeSet<String>,
+}
+
+pub(crate) type Schema = BTreeMap<String, TableSchema>;
+
+impl TableSchema {
+    fn compares_columns(&self) -> bool {
+        SchemaObjectKind::from_engine(&self.engine) == SchemaObjectKind::Table
+    }
+}
+
+#[derive(Debug, Row, Deserialize)]
+struct ColumnRow {
+    table: String,
+    name: String,
+    #[serde(rename = "type")]
+    column_type: String,
+}
+
+#[derive(Debug, Row, Deserialize)]
+struct IndexRow {
+    table: String,
+    name: String,
+}
+
+/// 读取数据库的实际结构，`excluded` 中的表除外
+pub(crate) async fn live_schema(client: &Client, excluded: &[String]) -> Result<Schema> {
+    let mut tables: Schema = schema::list_objects(client, excluded).await?
+        .into_iter()
+        .map(|row| (row.name, TableSchema { engine: row.engine, ..TableSchema::default() }))
+        .collect();
+
+    let columns = client
+        .query("SELECT table, name, type FROM system.columns WHERE database = currentDatabase() ORDER BY table, position")
+        .fetch_all::<ColumnRow>()
+        .await
+        .map_err(|e| anyhow!("Failed to read system.columns: {}", e))?;
+    for column in columns {
+        if let Some(table) = tables.get_mut(&column.table).filter(|table| table.compares_columns()) {
+            table.columns.push((column.name, normalize_type(&column.column_type)));
+        }
+    }
+
+    let indices = client
+        .query("SELECT table, name FROM system.data_skipping_indices WHERE database = currentDatabase()")
+        .fetch_all::<IndexRow>()
+        .await
+        .map_err(|e| anyhow!("Failed to read system.data_skipping_indices: {}", e))?;
+    for index in indices {
+        if let Some(table) = tables.get_mut(&index.table) {
+            table.indices.insert(index.name);
+        }
+    }
+
+    Ok(tables)
+}
+
+/// 解析 `schema.sql` 中的 CREATE 语句
+pub(crate) fn parse_snapshot(sql: &str) -> Result<Schema> {
+    let statements = lexer::split_statements(sql)
+        .map_err(|e| anyhow!("Failed to parse schema snapshot at {}:{}: {}", e.line, e.column, e.message))?;
+
+    let mut tables = Schema::new();
+    for statement in statements {
+        let tokens = significant_tokens(&statement.sql)
+            .map_err(|e| anyhow!("Failed to parse schema snapshot at line {}: {}", statement.line, e.message))?;
+        if let Some((name, table)) = parse_create(&statement, &tokens)? {
+            tables.insert(name, table);
+        }
+    }
+    Ok(tables)
+}
+
+/// 比较快照（expected）和数据库（actual），按表名排序
+pub(crate) fn compare(expected: &Schema, actual: &Schema) -> Vec<SchemaDrift> {
+    let mut drift = Vec::new();
+    let names: BTreeSet<&String> = expected.keys().chain(actual.keys()).collect();
+
+    for name in names {
+        let table = name.clone();
+        let (expected, actual) = match (expected.get(name), actual.get(name)) {
+            (Some(expected), Some(actual)) => (expected, actual),
+            (Some(_), None) => {
+                drift.push(SchemaDrift::MissingTable { table });
+                continue;
+            }
+            (None, _) => {
+                drift.push(SchemaDrift::ExtraTable { table });
+                continue;
+            }
+        };
+
+        if expected.engine != actual.engine {
+            drift.push(SchemaDrift::EngineDiffers {
+                table: table.clone(),
+                expected: expected.engine.clone(),
+                actual: actual.engine.clone(),
+            });
+        }
+
+        if expected.compares_columns() && actual.compares_columns() {
+            for (column, expected_type) in &expected.columns {
+                match actual.columns.iter().find(|(name, _)| name == column) {
+                    None => drift.push(SchemaDrift::MissingColumn {
+                        table: table.clone(),
+                        column: column.clone(),
+                        expected_type: expected_type.clone(),
+                    }),
+                    Some((_, actual_type)) if actual_type != expected_type => drift.push(SchemaDrift::TypeChanged {
+                        table: table.clone(),
+                        column: column.clone(),
+                        expected: expected_type.clone(),
+                        actual: actual_type.clone(),
+                    }),
+                    Some(_) => {}
+                }
+            }
+            for (column, actual_type) in &actual.columns {
+                if !expected.columns.iter().any(|(name, _)| name == column) {
+                    drift.push(SchemaDrift::ExtraColumn {
+                        table: table.clone(),
+                        column: column.clone(),
+                        actual_type: actual_type.clone(),
+                    });
+                }
+            }
+        }
+
+        for index in expected.indices.difference(&actual.indices) {
+            drift.push(SchemaDrift::MissingIndex { table: table.clone(), index: index.clone() });
+        }
+        for index in actual.indices.difference(&expected.indices) {
+            drift.push(SchemaDrift::ExtraIndex { table: table.clone(), index: index.clone() });
+        }
+    }
+
+    drift
+}
+
+/// 这些迁移会修改结构的表；包含 Rust 代码迁移或无法确定影响范围的语句时返回 None
+pub(crate) fn tables_affected_by(migrations: &[MigrationFile]) -> Option<BTreeSet<String>> {
+    let mut tables = BTreeSet::new();
+    for migration in migrations {
+        if migration.code.is_some() {
+            return None;
+        }
+        tables.extend(affected_tables(&migration.up_sql)?);
+    }
+    Some(tables)
+}
+
+/// 迁移 SQL 会修改结构的表；包含无法确定影响范围的语句（如 `CREATE/DROP DATABASE`）时返回 None
+pub(crate) fn affected_tables(sql: &str) -> Option<BTreeSet<String>> {
+    let mut tables = BTreeSet::new();
+    for statement in lexer::split_statements(sql).ok()? {
+        let tokens = significant_tokens(&statement.sql).ok()?;
+        let word = |index: usize| word_at(&tokens, index);
+
+        let mut index = 1;
+        match word(0).as_deref() {
+            Some("CREATE") | Some("DROP") | Some("ATTACH") | Some("DETACH") => {
+                while word(index).is_some_and(|w| matches!(w.as_str(), "OR" | "REPLACE" | "TEMPORARY" | "MATERIALIZED" | "LIVE" | "WINDOW")) {
+                    index += 1;
+                }
+                match word(index).as_deref() {
+                    Some("TABLE") | Some("VIEW") | Some("DICTIONARY") => {
+                        index += 1;
+                        while word(index).is_some_and(|w| matches!(w.as_str(), "IF" | "NOT" | "EXISTS")) {
+                            index += 1;
+                        }
+                        tables.insert(qualified_name(&tokens, &mut index)?);
+                    }
+                    Some("DATABASE") => return None,
+                    _ => {}
+                }
+            }
+            Some("ALTER") if word(1).as_deref() == Some("TABLE") => {
+                index = 2;
+                tables.insert(qualified_name(&tokens, &mut index)?);
+            }
+            // RENAME TABLE a TO b, c TO d / EXCHANGE TABLES a AND b
+            Some("RENAME") | Some("EXCHANGE") => {
+                index = 2;
+                loop {
+                    tables.insert(qualified_name(&tokens, &mut index)?);
+                    if !word(index).is_some_and(|w| w == "TO" || w == "AND") && !is_punct(&tokens, index, ",") {
+                        break;
+                    }
+                    index += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(tables)
+}
+
+/// 类型的规范化形式：去掉空白，避免格式差异
+fn normalize_type(column_type: &str) -> String {
+    match lexer::tokenize(column_type) {
+        Ok(tokens) => tokens.iter().filter(|token| !token.kind.is_trivia()).map(|token| token.text).collect(),
+        Err(_) => column_type.split_whitespace().collect(),
+    }
+}
+
+fn significant_tokens(sql: &str) -> std::result::Result<Vec<Token<'_>>, lexer::LexError> {
+    Ok(lexer::tokenize(sql)?.into_iter().filter(|token| !token.kind.is_trivia()).collect())
+}
+
+fn word_at(tokens: &[Token<'_>], index: usize) -> Option<String> {
+    tokens.get(index)
+        .filter(|token| token.kind == TokenKind::Word)
+        .map(|token| token.text.to_ascii_uppercase())
+}
+
+fn is_punct(tokens: &[Token<'_>], index: usize, punct: &str) -> bool {
+    tokens.get(index).is_some_and(|token| token.kind == TokenKind::Punct && token.text == punct)
+}
+
+fn unquote(text: &str) -> String {
+    for quote in ['`', '"'] {
+        if let Some(inner) = text.strip_prefix(quote).and_then(|t| t.strip_suffix(quote)) {
+            return inner.replace(&format!("\\{}", quote), &quote.to_string()).replace("\\\\", "\\");
+        }
+    }
+    text.to_string()
+}
+
+/// 读取 `[db.]name`，返回不含数据库名的名称，`index` 移到名称之后
+fn qualified_name(tokens: &[Token<'_>], index: &mut usize) -> Option<String> {
+    let mut name = None;
+    while let Some(token) = tokens.get(*index) {
+        if !matches!(token.kind, TokenKind::Word | TokenKind::QuotedIdentifier) {
+            break;
+        }
+        name = Some(unquote(token.text));
+        *index += 1;
+        if !is_punct(tokens, *index, ".") {
+            break;
+        }
+        *index += 1;
+    }
+    name
+}
+
+/// 解析一条 `CREATE` 语句的名称、引擎、列和索引；不是 `CREATE` 语句时返回 None，
+/// 列定义的括号不匹配时返回错误（位置为在快照文件中的行列号）
+fn parse_create(statement: &SqlStatement, tokens: &[Token<'_>]) -> Result<Option<(String, TableSchema)>> {
+    let sql = statement.sql.as_str();
+    let unbalanced = |token: &Token<'_>, message: &str| {
+        // token 的行列号相对于语句开头
+        let column = if token.line == 1 { statement.column + token.column - 1 } else { token.column };
+        anyhow!("Failed to parse schema snapshot at {}:{}: {}", statement.line + token.line - 1, column, message)
+    };
+
+    if word_at(tokens, 0).as_deref() != Some("CREATE") {
+        return Ok(None);
+    }
+
+    let mut index = 1;
+    let mut modifiers = Vec::new();
+    while let Some(word) = word_at(tokens, index).filter(|w| matches!(w.as_str(), "OR" | "REPLACE" | "TEMPORARY" | "MATERIALIZED" | "LIVE" | "WINDOW")) {
+        modifiers.push(word);
+        index += 1;
+    }
+    let Some(kind) = word_at(tokens, index) else {
+        return Ok(None);
+    };
+    let engine = match (kind.as_str(), modifiers.last().map(String::as_str)) {
+        ("TABLE", _) => None,
+        ("DICTIONARY", _) => Some("Dictionary"),
+        ("VIEW", Some("MATERIALIZED")) => Some("MaterializedView"),
+        ("VIEW", Some("LIVE")) => Some("LiveView"),
+        ("VIEW", Some("WINDOW")) => Some("WindowView"),
+        ("VIEW", _) => Some("View"),
+        _ => return Ok(None),
+    };
+    index += 1;
+    while word_at(tokens, index).is_some_and(|w| matches!(w.as_str(), "IF" | "NOT" | "EXISTS")) {
+        index += 1;
+    }
+    let Some(name) = qualified_name(tokens, &mut index) else {
+        return Ok(None);
+    };
+
+    let mut table = TableSchema::default();
+    if let Some(engine) = engine {
+        // 视图和字典只比较是否存在和类型
+        table.engine = engine.to_string();
+        return Ok(Some((name, table)));
+    }
+
+    // 列定义括号在 ENGINE / AS 之前（`CREATE TABLE t AS other` 没有列定义）
+    let rest = &tokens[index..];
+    let columns_start = rest.iter()
+        .position(|token| {
+            (token.kind == TokenKind::Punct && token.text == "(")
+                || (token.kind == TokenKind::Word && matches!(token.text.to_ascii_uppercase().as_str(), "ENGINE" | "AS"))
+        })
+        .filter(|&position| is_punct(rest, position, "("));
+
+    let mut position = 0;
+    if let Some(start) = columns_start {
+        let mut depth = 0usize;
+        let mut element_start = start + 1;
+        for (current, token) in rest.iter().enumerate().skip(start) {
+            match (token.kind, token.text) {
+                (TokenKind::Punct, "(" | "[") => depth += 1,
+                (TokenKind::Punct, ")" | "]") => {
+                    depth = depth.checked_sub(1)
+                        .ok_or_else(|| unbalanced(token, &format!("unmatched '{}'", token.text)))?;
+                    if depth == 0 {
+                        parse_element(sql, &rest[element_start..current], &mut table);
+                        position = current + 1;
+                        break;
+                    }
+                }
+                (TokenKind::Punct, ",") if depth == 1 => {
+                    parse_element(sql, &rest[element_start..current], &mut table);
+                    element_start = current + 1;
+                }
+                _ => {}
+            }
+        }
+        if position == 0 {
+            return Err(unbalanced(&rest[start], "unclosed column list"));
+        }
+    }
+
+    table.engine = (position..rest.len())
+        .find(|&current| word_at(rest, current).as_deref() == Some("ENGINE") && is_punct(rest, current + 1, "="))
+        .and_then(|current| rest.get(current + 2))
+        .map(|engine| engine.text.to_string())
+        .unwrap_or_default();
+    Ok(Some((name, table)))
+}
+
+/// 解析列定义括号中的一项：列、`INDEX`，其它（`PROJECTION`、`CONSTRAINT` 等）忽略
+fn parse_element(sql: &str, tokens: &[Token<'_>], table: &mut TableSchema) {
+    let Some(first) = tokens.first() else { return };
+    if first.kind == TokenKind::Word {
+        match first.text.to_ascii_uppercase().as_str() {
+            "INDEX" => {
+                if let Some(index) = tokens.get(1) {
+                    table.indices.insert(unquote(index.text));
+                }
+                return;
+            }
+            "PROJECTION" | "CONSTRAINT" | "PRIMARY" => return,
+            _ => {}
+        }
+    }
+
+    // 类型从列名之后开始，到同层的列属性关键字为止
+    let mut depth = 0usize;
+    let mut end = None;
+    let mut attribute = None;
+    for (position, token) in tokens.iter().enumerate().skip(1) {
+        match (token.kind, token.text) {
+            (TokenKind::Punct, "(" | "[") => depth += 1,
+            (TokenKind::Punct, ")" | "]") => depth = depth.saturating_sub(1),
+            (TokenKind::Word, word) if depth == 0 && is_column_attribute(word) => {
+                attribute = Some(word.to_ascii_uppercase());
+                break;
+            }
+            _ => {}
+        }
+        end = Some(position);
+    }
+    let (Some(type_start), Some(end)) = (tokens.get(1), end.map(|end| &tokens[end])) else {
+        return;
+    };
+    let column_type = normalize_type(&sql[type_start.offset..end.offset + end.text.len()]);
+    // `x String NULL` 在 system.columns 中为 Nullable(String)；`NOT NULL` 不改变类型
+    let column_type = match attribute.as_deref() {
+        Some("NULL") => format!("Nullable({})", column_type),
+        _ => column_type,
+    };
+    table.columns.push((unquote(first.text), column_type));
+}
+
+fn is_column_attribute(word: &str) -> bool {
+    matches!(
+        word.to_ascii_uppercase().as_str(),
+        "DEFAULT" | "MATERIALIZED" | "ALIAS" | "EPHEMERAL" | "CODEC" | "TTL" | "COMMENT" | "STATISTICS" | "SETTINGS" | "NULL" | "NOT"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT: &str = "\
+-- 表
+CREATE TABLE users
+(
+    `id` UInt64,
+    `name` String DEFAULT '',
+    `tags` Array(LowCardinality(String)) CODEC(ZSTD(1)),
+    `amount` Decimal(18, 2),
+    `email` String NULL,
+    `phone` String NOT NULL DEFAULT '',
+    INDEX idx_name name TYPE bloom_filter GRANULARITY 4,
+    PROJECTION p (SELECT id ORDER BY name)
+)
+ENGINE = MergeTree
+ORDER BY id
+SETTINGS index_granularity = 8192;
+
+CREATE MATERIALIZED VIEW users_mv TO users_copy AS SELECT * FROM users;
+
+CREATE DICTIONARY user_names (id UInt64, name String) PRIMARY KEY id SOURCE(CLICKHOUSE(TABLE 'users')) LAYOUT(FLAT()) LIFETIME(0);
+";
+
+    fn table(engine: &str, columns: &[(&str, &str)], indices: &[&str]) -> TableSchema {
+        TableSchema {
+            engine: engine.to_string(),
+            columns: columns.iter().map(|(name, t)| (name.to_string(), t.to_string())).collect(),
+            indices: indices.iter().map(|index| index.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_snapshot_objects() {
+        let schema = parse_snapshot(SNAPSHOT).unwrap();
+        assert_eq!(schema.keys().collect::<Vec<_>>(), ["user_names", "users", "users_mv"]);
+
+        let users = &schema["users"];
+        assert_eq!(users.engine, "MergeTree");
+        assert_eq!(users.columns, [
+            ("id".to_string(), "UInt64".to_string()),
+            ("name".to_string(), "String".to_string()),
+            ("tags".to_string(), "Array(LowCardinality(String))".to_string()),
+            ("amount".to_string(), "Decimal(18,2)".to_string()),
+            ("email".to_string(), "Nullable(String)".to_string()),
+            ("phone".to_string(), "String".to_string()),
+        ]);
+        assert_eq!(users.indices.iter().collect::<Vec<_>>(), ["idx_name"]);
+
+        assert_eq!(schema["users_mv"].engine, "MaterializedView");
+        assert_eq!(schema["user_names"].engine, "Dictionary");
+        assert!(schema["user_names"].columns.is_empty());
+    }
+
+    #[test]
+    fn reports_unclosed_column_list_with_location() {
+        let snapshot = "SELECT 1;\n  CREATE TABLE t (id Nullable(UInt64) ENGINE = Log;\n";
+        let error = parse_snapshot(snapshot).unwrap_err().to_string();
+        assert_eq!(error, "Failed to parse schema snapshot at 2:18: unclosed column list");
+    }
+
+    #[test]
+    fn compares_tables_columns_and_indices() {
+        let mut expected = Schema::new();
+        expected.insert("users".to_string(), table("MergeTree", &[("id", "UInt64"), ("name", "String")], &["idx_name"]));
+        expected.insert("events".to_string(), table("MergeTree", &[("id", "UInt64")], &[]));
+        expected.insert("v".to_string(), table("View", &[], &[]));
+
+        let mut actual = Schema::new();
+        actual.insert(
+            "users".to_string(),
+            table("ReplacingMergeTree", &[("id", "UInt32"), ("email", "String")], &["idx_email"]),
+        );
+        actual.insert("v".to_string(), table("View", &[], &[]));
+        actual.insert("tmp".to_string(), table("Log", &[("x", "UInt8")], &[]));
+
+        let s = |s: &str| s.to_string();
+        assert_eq!(compare(&expected, &actual), vec![
+            SchemaDrift::MissingTable { table: s("events") },
+            SchemaDrift::ExtraTable { table: s("tmp") },
+            SchemaDrift::EngineDiffers { table: s("users"), expected: s("MergeTree"), actual: s("ReplacingMergeTree") },
+            SchemaDrift::TypeChanged { table: s("users"), column: s("id"), expected: s("UInt64"), actual: s("UInt32") },
+            SchemaDrift::MissingColumn { table: s("users"), column: s("name"), expected_type: s("String") },
+            SchemaDrift::ExtraColumn { table: s("users"), column: s("email"), actual_type: s("String") },
+            SchemaDrift::MissingIndex { table: s("users"), index: s("idx_name") },
+            SchemaDrift::ExtraIndex { table: s("users"), index: s("idx_email") },
+        ]);
+
+        assert!(compare(&expected, &expected).is_empty());
+    }
+}
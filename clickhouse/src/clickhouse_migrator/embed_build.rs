@@ -0,0 +1,94 @@
+//! 构建脚本辅助：在编译期把迁移目录嵌入二进制。
+//!
+//! 嵌入前用运行时相同的解析（`parser`）检查每个文件：文件名、版本号、执行指令、
+//! SQL 词法和占位符格式有误时构建失败，不会等到启动时才发现。
+//! 本 crate 的 `build.rs` 通过 `#[path]` 引用本文件和它依赖的解析模块。
+//! 在自己的 crate 中使用时，将本库加入 `[build-dependencies]`，并在 `build.rs` 中调用：
+//!
+//! ```text
+//! fn main() {
+//!     clickhouse_connector::clickhouse_migrator::embed_build::generate_embedded_migrations("migrations")
+//!         .expect("failed to embed migrations");
+//! }
+//! ```
+//!
+//! 然后在代码中通过 `embed_clickhouse_migrations!("migrations")` 取得嵌入的迁移集合。
+
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use super::parser;
+
+/// 生成文件在 `OUT_DIR` 下的位置，需与 `embed_clickhouse_migrations!` 保持一致
+pub fn generated_file_path(out_dir: &Path, migrations_dir: &str) -> PathBuf {
+    out_dir
+        .join("clickhouse_migrations")
+        .join(migrations_dir)
+        .join("embedded.rs")
+}
+
+/// 扫描 `CARGO_MANIFEST_DIR` 下的迁移目录，生成 `include_str!` 列表
+///
+/// 文件无法解析或版本号重复时返回错误，使构建失败；目录不存在时生成空集合并输出警告。
+pub fn generate_embedded_migrations(migrations_dir: &str) -> Result<()> {
+    let manifest_dir = env_path("CARGO_MANIFEST_DIR")?;
+    let out_dir = env_path("OUT_DIR")?;
+    let source_dir = manifest_dir.join(migrations_dir);
+
+    println!("cargo:rerun-if-changed={}", source_dir.display());
+
+    let mut files = Vec::new();
+    if source_dir.is_dir() {
+        for entry in std::fs::read_dir(&source_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("sql") {
+                files.push(path);
+            }
+        }
+    } else {
+        println!("cargo:warning=Migration directory does not exist: {}", source_dir.display());
+    }
+    files.sort();
+
+    let mut versions: BTreeMap<u32, String> = BTreeMap::new();
+    let mut generated = String::from("&[\n");
+    for path in &files {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let file_name = path.file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| invalid(format!("Invalid migration filename: {:?}", path)))?;
+        let content = std::fs::read_to_string(path)?;
+        let (parsed_name, _) = parser::parse_migration(file_name, &content)
+            .map_err(|e| invalid(format!("Failed to parse migration {}: {:#}", file_name, e)))?;
+        if let Some(version) = parsed_name.number {
+            if let Some(existing) = versions.insert(version, file_name.to_string()) {
+                return Err(invalid(format!(
+                    "Duplicate migration version {}: {} and {}",
+                    version, existing, file_name
+                )));
+            }
+        }
+
+        let absolute = path.canonicalize()?;
+        let absolute = absolute.to_str()
+            .ok_or_else(|| invalid(format!("Non UTF-8 migration path: {:?}", absolute)))?;
+        generated.push_str(&format!("    ({:?}, include_str!({:?})),\n", file_name, absolute));
+    }
+    generated.push(']');
+
+    let target = generated_file_path(&out_dir, migrations_dir);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&target, generated)
+}
+
+fn env_path(name: &str) -> Result<PathBuf> {
+    std::env::var_os(name)
+        .map(PathBuf::from)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("{} is not set (must be called from build.rs)", name)))
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,59 @@
+//! 编译期嵌入的迁移文件，以及迁移文件的来源（目录或嵌入集合）
+
+/// 编译期嵌入的迁移文件集合，通过 `embed_clickhouse_migrations!` 创建
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedMigrations {
+    /// (文件名, 文件内容)，按文件名排序
+    files: &'static [(&'static str, &'static str)],
+}
+
+impl EmbeddedMigrations {
+    pub const fn new(files: &'static [(&'static str, &'static str)]) -> Self {
+        Self { files }
+    }
+
+    pub fn files(&self) -> &'static [(&'static str, &'static str)] {
+        self.files
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// 迁移文件来源
+#[derive(Debug, Clone)]
+pub enum MigrationSource {
+    /// 运行时从目录读取
+    Directory(String),
+    /// 编译期嵌入二进制
+    Embedded(EmbeddedMigrations),
+}
+
+impl std::fmt::Display for MigrationSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationSource::Directory(path) => write!(f, "{}", path),
+            MigrationSource::Embedded(migrations) => write!(f, "embedded migrations ({} files)", migrations.len()),
+        }
+    }
+}
+
+/// 取得 `build.rs` 中 `generate_embedded_migrations(dir)` 嵌入的迁移集合
+///
+/// 参数需与 `build.rs` 中传入的目录完全一致。
+#[macro_export]
+macro_rules! embed_clickhouse_migrations {
+    ($dir:literal) => {
+        $crate::clickhouse_migrator::EmbeddedMigrations::new(include!(concat!(
+            env!("OUT_DIR"),
+            "/clickhouse_migrations/",
+            $dir,
+            "/embedded.rs"
+        )))
+    };
+}
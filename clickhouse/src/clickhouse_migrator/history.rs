@@ -0,0 +1,94 @@
+//! 迁移历史表：每次执行尝试一行，按 (version, run_id, attempt) 去重
+
+/// 判断历史表是否为新表结构的标志列
+pub(crate) const SCHEMA_MARKER_COLUMN: &str = "run_id";
+
+/// 新表结构建立后增加的列，已有的历史表通过 `ADD COLUMN` 补齐
+pub(crate) const ADDED_COLUMNS: [(&str, &str); 5] = [
+    ("query_ids", "Array(String) DEFAULT []"),
+    ("read_rows", "UInt64 DEFAULT 0"),
+    ("read_bytes", "UInt64 DEFAULT 0"),
+    ("memory_usage", "UInt64 DEFAULT 0"),
+    ("query_duration_ms", "UInt64 DEFAULT 0"),
+];
+
+/// 一次迁移运行（一次 `migrate()` 调用）的上下文，该次运行的所有尝试记录共享
+#[derive(Debug, Clone)]
+pub(crate) struct RunContext {
+    pub run_id: String,
+    pub hostname: String,
+    pub os_user: String,
+}
+
+impl RunContext {
+    pub(crate) fn new() -> Self {
+        Self {
+            run_id: uuid::Uuid::new_v4().to_string(),
+            hostname: hostname::get()
+                .map(|h| h.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            os_user: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// 历史表建表语句；`engine` 为 ReplacingMergeTree 或其复制版本
+pub(crate) fn create_table_sql(table_name: &str, on_cluster: &str, engine: &str) -> String {
+    format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {table_name}{on_cluster} (
+            run_id String,
+            version String,
+            attempt UInt32,
+            name String,
+            checksum String,
+            success UInt8,
+            error_message String DEFAULT '',
+            execution_time_ms UInt64,
+            started_at DateTime64(3),
+            finished_at DateTime64(3),
+            hostname String DEFAULT '',
+            os_user String DEFAULT '',
+            tool_version String DEFAULT '',
+            statements_total UInt32 DEFAULT 0,
+            statements_completed UInt32 DEFAULT 0,
+            note String DEFAULT '',
+            query_ids Array(String) DEFAULT [],
+            read_rows UInt64 DEFAULT 0,
+            read_bytes UInt64 DEFAULT 0,
+            memory_usage UInt64 DEFAULT 0,
+            query_duration_ms UInt64 DEFAULT 0
+        ) ENGINE = {engine}
+        ORDER BY (version, run_id, attempt)
+        SETTINGS index_granularity = 8192
+        "#
+    )
+}
+
+/// 将旧表结构（每次保存一行，ORDER BY version）的记录复制到新表
+///
+/// 旧记录统一归入 `legacy` 运行，按 applied_at 顺序编号尝试次数。
+pub(crate) fn copy_legacy_rows_sql(from_table: &str, to_table: &str) -> String {
+    format!(
+        r#"
+        INSERT INTO {to_table}
+            (run_id, version, attempt, name, checksum, success, error_message, execution_time_ms,
+             started_at, finished_at, hostname, os_user, tool_version, statements_total, statements_completed, note)
+        SELECT
+            'legacy',
+            version,
+            toUInt32(row_number() OVER (PARTITION BY version ORDER BY applied_at)),
+            name,
+            checksum,
+            success,
+            error_message,
+            execution_time_ms,
+            applied_at - toIntervalMillisecond(execution_time_ms),
+            applied_at,
+            '', '', '', 0, 0, ''
+        FROM {from_table}
+        "#
+    )
+}
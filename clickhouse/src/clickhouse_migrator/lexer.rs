@@ -0,0 +1,434 @@
+//! ClickHouse SQL 词法分析：按 ClickHouse 的词法规则切分 token，并据此分割多语句 SQL。
+
+/// token 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    /// `-- ...`、`# ...`、`#! ...` 单行注释
+    LineComment,
+    /// `/* ... */` 块注释（支持嵌套）
+    BlockComment,
+    /// 关键字、标识符或数字
+    Word,
+    /// `'...'` 字符串字面量
+    StringLiteral,
+    /// `"..."` 或 `` `...` `` 引用标识符
+    QuotedIdentifier,
+    /// `$tag$ ... $tag$` heredoc 字符串
+    Heredoc,
+    Semicolon,
+    /// 其它单字符运算符或标点
+    Punct,
+}
+
+impl TokenKind {
+    /// 是否为不影响语义的空白或注释
+    pub fn is_trivia(self) -> bool {
+        matches!(self, TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// 在输入中的字节偏移
+    pub offset: usize,
+    /// 1 起始的行号
+    pub line: usize,
+    /// 1 起始的列号（按字符计）
+    pub column: usize,
+}
+
+/// 分割后的单条语句及其在输入中的起始位置
+#[derive(Debug, Clone)]
+pub struct SqlStatement {
+    pub sql: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct LexError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}:{}", self.message, self.line, self.column)
+    }
+}
+
+impl std::error::Error for LexError {}
+
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    fn error(&self, message: &str, line: usize, column: usize) -> LexError {
+        LexError { message: message.to_string(), line, column }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, LexError> {
+        let start = self.pos;
+        let (line, column) = (self.line, self.column);
+
+        let Some(ch) = self.peek() else {
+            return Ok(None);
+        };
+
+        let kind = match ch {
+            c if c.is_whitespace() => {
+                while self.peek().is_some_and(char::is_whitespace) {
+                    self.bump();
+                }
+                TokenKind::Whitespace
+            }
+            '-' if self.peek_nth(1) == Some('-') => {
+                self.skip_line();
+                TokenKind::LineComment
+            }
+            '#' if matches!(self.peek_nth(1), Some(' ') | Some('!')) => {
+                self.skip_line();
+                TokenKind::LineComment
+            }
+            '/' if self.peek_nth(1) == Some('*') => {
+                self.skip_block_comment(line, column)?;
+                TokenKind::BlockComment
+            }
+            '\'' => {
+                self.skip_quoted('\'', "Unterminated string literal", line, column)?;
+                TokenKind::StringLiteral
+            }
+            '"' | '`' => {
+                self.skip_quoted(ch, "Unterminated quoted identifier", line, column)?;
+                TokenKind::QuotedIdentifier
+            }
+            '$' if self.heredoc_tag().is_some() => {
+                self.skip_heredoc(line, column)?;
+                TokenKind::Heredoc
+            }
+            ';' => {
+                self.bump();
+                TokenKind::Semicolon
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                    self.bump();
+                }
+                TokenKind::Word
+            }
+            _ => {
+                self.bump();
+                TokenKind::Punct
+            }
+        };
+
+        Ok(Some(Token {
+            kind,
+            text: &self.input[start..self.pos],
+            offset: start,
+            line,
+            column,
+        }))
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(ch) = self.peek() {
+            if ch == '\n' {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn skip_block_comment(&mut self, line: usize, column: usize) -> Result<(), LexError> {
+        self.bump();
+        self.bump();
+        let mut depth = 1;
+
+        while depth > 0 {
+            match (self.peek(), self.peek_nth(1)) {
+                (Some('/'), Some('*')) => {
+                    self.bump();
+                    self.bump();
+                    depth += 1;
+                }
+                (Some('*'), Some('/')) => {
+                    self.bump();
+                    self.bump();
+                    depth -= 1;
+                }
+                (Some(_), _) => {
+                    self.bump();
+                }
+                (None, _) => return Err(self.error("Unterminated block comment", line, column)),
+            }
+        }
+        Ok(())
+    }
+
+    /// 跳过引号包围的内容，支持反斜杠转义和连续两个引号的转义
+    fn skip_quoted(&mut self, quote: char, message: &str, line: usize, column: usize) -> Result<(), LexError> {
+        self.bump();
+
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    if self.bump().is_none() {
+                        return Err(self.error(message, line, column));
+                    }
+                }
+                Some(c) if c == quote => {
+                    if self.peek() == Some(quote) {
+                        self.bump();
+                    } else {
+                        return Ok(());
+                    }
+                }
+                Some(_) => {}
+                None => return Err(self.error(message, line, column)),
+            }
+        }
+    }
+
+    /// 当前位置的 heredoc 起始标记（如 `$$`、`$body$`）
+    fn heredoc_tag(&self) -> Option<&'a str> {
+        let rest = &self.input[self.pos..];
+        let tag_len = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))?;
+        if rest[1 + tag_len..].starts_with('$') {
+            Some(&rest[..tag_len + 2])
+        } else {
+            None
+        }
+    }
+
+    fn skip_heredoc(&mut self, line: usize, column: usize) -> Result<(), LexError> {
+        let tag = self.heredoc_tag().unwrap_or("$$");
+        for _ in tag.chars() {
+            self.bump();
+        }
+
+        match self.input[self.pos..].find(tag) {
+            Some(end) => {
+                let target = self.pos + end + tag.len();
+                while self.pos < target {
+                    self.bump();
+                }
+                Ok(())
+            }
+            None => Err(self.error("Unterminated heredoc", line, column)),
+        }
+    }
+}
+
+/// 将 SQL 切分为 token 序列
+pub fn tokenize(sql: &str) -> Result<Vec<Token<'_>>, LexError> {
+    let mut lexer = Lexer { input: sql, pos: 0, line: 1, column: 1 };
+    let mut tokens = Vec::new();
+
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// 按顶层分号分割多语句 SQL，丢弃只包含空白和注释的语句
+///
+/// 每条语句从第一个有效 token 开始，到分号前最后一个有效 token 结束。
+pub fn split_statements(sql: &str) -> Result<Vec<SqlStatement>, LexError> {
+    let tokens = tokenize(sql)?;
+    let mut statements = Vec::new();
+    let mut current: Option<(Token<'_>, usize)> = None;
+
+    for token in &tokens {
+        match token.kind {
+            TokenKind::Semicolon => {
+                if let Some((first, end)) = current.take() {
+                    statements.push(SqlStatement {
+                        sql: sql[first.offset..end].to_string(),
+                        line: first.line,
+                        column: first.column,
+                    });
+                }
+            }
+            kind if kind.is_trivia() => {}
+            _ => {
+                let end = token.offset + token.text.len();
+                match current.as_mut() {
+                    Some((_, current_end)) => *current_end = end,
+                    None => current = Some((*token, end)),
+                }
+            }
+        }
+    }
+
+    if let Some((first, end)) = current {
+        statements.push(SqlStatement {
+            sql: sql[first.offset..end].to_string(),
+            line: first.line,
+            column: first.column,
+        });
+    }
+
+    Ok(statements)
+}
+
+/// 将整段 SQL 作为一条语句（不按分号分割），去掉首尾的空白、注释和末尾的分号
+pub fn single_statement(sql: &str) -> Result<Option<SqlStatement>, LexError> {
+    let tokens = tokenize(sql)?;
+    let is_significant = |token: &&Token<'_>| !token.kind.is_trivia() && token.kind != TokenKind::Semicolon;
+
+    let (Some(first), Some(last)) = (tokens.iter().find(is_significant), tokens.iter().rfind(is_significant)) else {
+        return Ok(None);
+    };
+
+    Ok(Some(SqlStatement {
+        sql: sql[first.offset..last.offset + last.text.len()].to_string(),
+        line: first.line,
+        column: first.column,
+    }))
+}
+
+/// 迁移文件中一段 SQL（Up 或 Down 部分）每一行对应的原始文件行号
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    file_name: String,
+    /// 第 i 行对应的 (文件行号, 列偏移)
+    lines: Vec<(usize, usize)>,
+}
+
+impl SourceMap {
+    pub(crate) fn new(file_name: &str, lines: Vec<(usize, usize)>) -> Self {
+        Self { file_name: file_name.to_string(), lines }
+    }
+
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// 将片段内的行列号换算为文件中的位置，格式为 `file:line:column`
+    pub fn locate(&self, line: usize, column: usize) -> String {
+        let (file_line, file_column) = match self.lines.get(line.saturating_sub(1)) {
+            Some(&(file_line, column_offset)) => (file_line, column + column_offset),
+            None => (line, column),
+        };
+
+        if self.file_name.is_empty() {
+            format!("{}:{}", file_line, file_column)
+        } else {
+            format!("{}:{}:{}", self.file_name, file_line, file_column)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(sql: &str) -> Vec<String> {
+        split_statements(sql).unwrap().into_iter().map(|statement| statement.sql).collect()
+    }
+
+    #[test]
+    fn splits_on_top_level_semicolons() {
+        assert_eq!(split("SELECT 1; SELECT 2;\n\nSELECT 3"), ["SELECT 1", "SELECT 2", "SELECT 3"]);
+        assert_eq!(split(" ;; -- only a comment\n; /* and another */ "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn ignores_semicolons_in_string_literals() {
+        assert_eq!(
+            split("INSERT INTO t VALUES ('a;b'); SELECT 'it''s; fine', 'it\\'s; fine'"),
+            ["INSERT INTO t VALUES ('a;b')", "SELECT 'it''s; fine', 'it\\'s; fine'"]
+        );
+    }
+
+    #[test]
+    fn ignores_semicolons_in_quoted_identifiers() {
+        assert_eq!(
+            split("CREATE TABLE `a;b` (\"c;d\" String) ENGINE = Memory; SELECT 1"),
+            ["CREATE TABLE `a;b` (\"c;d\" String) ENGINE = Memory", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn ignores_semicolons_in_comments() {
+        let sql = "SELECT 1 -- first; not a split\n;\n/* outer; /* nested; */ still comment; */ SELECT 2;\n# hash; comment\nSELECT 3";
+        assert_eq!(split(sql), ["SELECT 1", "SELECT 2", "SELECT 3"]);
+        assert_eq!(split("SELECT 1 /* a; */ + 2"), ["SELECT 1 /* a; */ + 2"]);
+    }
+
+    #[test]
+    fn ignores_semicolons_in_heredocs() {
+        assert_eq!(split("SELECT $body$a;b$body$; SELECT $$c;$$"), ["SELECT $body$a;b$body$", "SELECT $$c;$$"]);
+    }
+
+    #[test]
+    fn reports_unterminated_literals_and_comments() {
+        let error = split_statements("SELECT 1;\nSELECT 'abc;").unwrap_err();
+        assert_eq!((error.message.as_str(), error.line, error.column), ("Unterminated string literal", 2, 8));
+
+        let error = split_statements("SELECT `abc").unwrap_err();
+        assert_eq!(error.message, "Unterminated quoted identifier");
+
+        let error = split_statements("SELECT 1 /* outer /* inner */ still open").unwrap_err();
+        assert_eq!((error.message.as_str(), error.line, error.column), ("Unterminated block comment", 1, 10));
+
+        let error = split_statements("SELECT 'ends with escape\\").unwrap_err();
+        assert_eq!(error.message, "Unterminated string literal");
+    }
+
+    #[test]
+    fn records_statement_positions() {
+        let statements = split_statements("-- header\nSELECT 1;\n  SELECT\n2;").unwrap();
+        let positions: Vec<(usize, usize)> = statements.iter().map(|s| (s.line, s.column)).collect();
+        assert_eq!(positions, [(2, 1), (3, 3)]);
+    }
+
+    #[test]
+    fn single_statement_keeps_inner_semicolons() {
+        let statement = single_statement("\n-- comment\nSELECT 1; SELECT 2;\n").unwrap().unwrap();
+        assert_eq!(statement.sql, "SELECT 1; SELECT 2");
+        assert_eq!((statement.line, statement.column), (3, 1));
+        assert!(single_statement("-- nothing\n;").unwrap().is_none());
+    }
+
+    #[test]
+    fn source_map_locates_positions_in_file() {
+        let map = SourceMap::new("V001__init.sql", vec![(5, 0), (6, 0), (8, 2)]);
+        assert_eq!(map.locate(1, 1), "V001__init.sql:5:1");
+        assert_eq!(map.locate(3, 4), "V001__init.sql:8:6");
+        // 超出映射范围时保留片段内的位置
+        assert_eq!(map.locate(10, 1), "V001__init.sql:10:1");
+        assert_eq!(SourceMap::new("", vec![(3, 0)]).locate(1, 2), "3:2");
+    }
+}
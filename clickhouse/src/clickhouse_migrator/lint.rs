@@ -0,0 +1,451 @@
+//! 迁移 SQL 的静态检查：只读取迁移文件，不访问服务器
+//!
+//! | 规则 | 名称 | 默认级别 | 检查内容 |
+//! |------|------|----------|----------|
+//! | CH001 | varchar-length | warning | `VARCHAR(n)` 在 ClickHouse 中就是 String，长度不生效 |
+//! | CH002 | drop-without-down | warning | Up 中 `DROP TABLE/VIEW/DICTIONARY/DATABASE`，但没有 Down 部分 |
+//! | CH003 | create-without-if-not-exists | warning | `CREATE TABLE/VIEW/DICTIONARY/DATABASE` 没有 `IF NOT EXISTS`，重试不幂等 |
+//! | CH004 | mutation-without-where | error | `ALTER TABLE ... UPDATE/DELETE` 或 `DELETE FROM` 没有有效的 WHERE 条件 |
+//! | CH005 | empty-up | error | Up 部分只有注释没有语句（如未编辑的新建模板），执行时什么也不做就被记录为已应用 |
+//!
+//! 规则级别可以在配置中调整（`off`、`warning`、`error`）；单个迁移文件可以用
+//! `-- +lint-ignore CH001` 忽略规则（该指令行不计入校验和，可以加在已应用的迁移上）。
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use super::lexer::{self, SourceMap, SqlStatement, Token, TokenKind};
+use super::simple_migrator::MigrationFile;
+
+pub use super::lint_rule::{LintRule, Severity};
+
+/// 规则级别配置，未设置的规则使用默认级别
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    severities: BTreeMap<LintRule, Severity>,
+}
+
+impl LintConfig {
+    pub fn severity(&self, rule: LintRule) -> Severity {
+        self.severities.get(&rule).copied().unwrap_or_else(|| rule.default_severity())
+    }
+
+    pub fn set(&mut self, rule: LintRule, severity: Severity) {
+        self.severities.insert(rule, severity);
+    }
+
+    /// 从配置项（规则代码或名称 → 级别）创建
+    pub fn from_map(rules: &BTreeMap<String, String>) -> Result<Self> {
+        let mut config = Self::default();
+        for (rule, severity) in rules {
+            config.set(LintRule::parse(rule)?, severity.parse()?);
+        }
+        Ok(config)
+    }
+}
+
+/// 一条检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct LintIssue {
+    pub rule: LintRule,
+    pub severity: Severity,
+    pub file_name: String,
+    /// `file:line:column`
+    pub location: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} {}: {}", self.location, self.severity, self.rule, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LintReport {
+    pub files_checked: usize,
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    pub fn error_count(&self) -> usize {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Error).count()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.issues.iter().filter(|issue| issue.severity == Severity::Warning).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+}
+
+impl std::fmt::Display for LintReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+        write!(
+            f,
+            "Checked {} migration files: {} errors, {} warnings",
+            self.files_checked,
+            self.error_count(),
+            self.warning_count()
+        )
+    }
+}
+
+/// 检查所有迁移文件；Rust 代码迁移和基线迁移不检查
+pub fn lint_migrations<'a>(
+    migrations: impl IntoIterator<Item = &'a MigrationFile>,
+    config: &LintConfig,
+) -> Result<LintReport> {
+    let mut report = LintReport::default();
+    for migration in migrations {
+        if migration.code.is_some() || migration.is_baseline {
+            continue;
+        }
+        report.files_checked += 1;
+        report.issues.extend(lint_migration(migration, config)?);
+    }
+    Ok(report)
+}
+
+/// 检查单个迁移文件，SQL 无法分割（如字符串未闭合）时返回错误
+pub fn lint_migration(migration: &MigrationFile, config: &LintConfig) -> Result<Vec<LintIssue>> {
+    let mut linter = Linter { migration, config, issues: Vec::new() };
+    let has_down = migration.down_sql.as_deref().is_some_and(|down| !down.trim().is_empty());
+
+    let up_statements = linter.statements(&migration.up_sql, &migration.up_source)?;
+    if up_statements.is_empty() {
+        let message = "Up section has only comments and no SQL statements, the migration would be recorded as applied without doing anything".to_string();
+        linter.report_at(LintRule::EmptyUp, migration.file_name.clone(), message);
+    }
+    for sql_statement in up_statements {
+        let statement = Statement::new(&sql_statement, &migration.up_source)?;
+        linter.check_varchar(&statement);
+        linter.check_create(&statement);
+        linter.check_mutation(&statement);
+        if !has_down && !migration.repeatable {
+            linter.check_drop(&statement);
+        }
+    }
+    if let Some(down_sql) = &migration.down_sql {
+        for sql_statement in linter.statements(down_sql, &migration.down_source)? {
+            let statement = Statement::new(&sql_statement, &migration.down_source)?;
+            linter.check_varchar(&statement);
+            linter.check_mutation(&statement);
+        }
+    }
+
+    Ok(linter.issues)
+}
+
+/// 一条语句的有效 token（去掉空白和注释），位置已换算为所在 Up/Down 部分中的行列号
+struct Statement<'a> {
+    tokens: Vec<Token<'a>>,
+    source: &'a SourceMap,
+}
+
+impl<'a> Statement<'a> {
+    /// 分析一条语句的 token，行列号换算为在所在部分中的位置
+    fn new(statement: &'a SqlStatement, source: &'a SourceMap) -> Result<Self> {
+        let tokens = lexer::tokenize(&statement.sql)
+            .map_err(|e| anyhow!("{}: {}", source.locate(statement.line + e.line - 1, e.column), e.message))?
+            .into_iter()
+            .filter(|token| !token.kind.is_trivia())
+            .map(|mut token| {
+                if token.line == 1 {
+                    token.column += statement.column - 1;
+                }
+                token.line += statement.line - 1;
+                token
+            })
+            .collect();
+        Ok(Self { tokens, source })
+    }
+
+    fn word(&self, index: usize) -> Option<String> {
+        self.tokens.get(index)
+            .filter(|token| token.kind == TokenKind::Word)
+            .map(|token| token.text.to_ascii_uppercase())
+    }
+
+    fn is_word(&self, index: usize, word: &str) -> bool {
+        self.word(index).is_some_and(|w| w == word)
+    }
+
+    fn is_punct(&self, index: usize, punct: &str) -> bool {
+        self.tokens.get(index).is_some_and(|token| token.kind == TokenKind::Punct && token.text == punct)
+    }
+
+    fn locate(&self, index: usize) -> String {
+        let token = &self.tokens[index];
+        self.source.locate(token.line, token.column)
+    }
+
+    /// 每个 token 所在的括号深度
+    fn depths(&self) -> Vec<usize> {
+        let mut depth = 0usize;
+        self.tokens.iter()
+            .map(|token| {
+                if token.kind != TokenKind::Punct {
+                    return depth;
+                }
+                match token.text {
+                    "(" | "[" => {
+                        depth += 1;
+                        depth - 1
+                    }
+                    ")" | "]" => {
+                        depth = depth.saturating_sub(1);
+                        depth
+                    }
+                    _ => depth,
+                }
+            })
+            .collect()
+    }
+}
+
+struct Linter<'a> {
+    migration: &'a MigrationFile,
+    config: &'a LintConfig,
+    issues: Vec<LintIssue>,
+}
+
+impl<'a> Linter<'a> {
+    /// 与执行时相同的语句分割：按顶层分号分割，`-- +nosplit` 时整段作为一条语句
+    fn statements(&self, sql: &str, source: &SourceMap) -> Result<Vec<SqlStatement>> {
+        let split = if self.migration.directives.no_split {
+            lexer::single_statement(sql).map(|statement| statement.into_iter().collect())
+        } else {
+            lexer::split_statements(sql)
+        };
+        split.map_err(|e| anyhow!("{}: {}", source.locate(e.line, e.column), e.message))
+    }
+
+    fn report(&mut self, rule: LintRule, statement: &Statement<'_>, index: usize, message: String) {
+        self.report_at(rule, statement.locate(index), message);
+    }
+
+    /// `location` 为 `file:line:column`，针对整个文件的问题为文件名
+    fn report_at(&mut self, rule: LintRule, location: String, message: String) {
+        let severity = self.config.severity(rule);
+        if severity == Severity::Off || self.migration.directives.lint_ignore.contains(&rule) {
+            return;
+        }
+        self.issues.push(LintIssue {
+            rule,
+            severity,
+            file_name: self.migration.file_name.clone(),
+            location,
+            message,
+        });
+    }
+
+    /// CH001：`VARCHAR(n)` 映射为 String，长度限制不生效
+    fn check_varchar(&mut self, statement: &Statement<'_>) {
+        for index in 0..statement.tokens.len() {
+            let Some(word) = statement.word(index) else { continue };
+            if matches!(word.as_str(), "VARCHAR" | "NVARCHAR" | "VARCHAR2") && statement.is_punct(index + 1, "(") {
+                let message = format!(
+                    "{}(n) is an alias of String in ClickHouse and the length is not enforced; use String or FixedString(n)",
+                    statement.tokens[index].text
+                );
+                self.report(LintRule::VarcharLength, statement, index, message);
+            }
+        }
+    }
+
+    /// CH002：删除对象但没有 Down 部分，迁移无法回滚
+    fn check_drop(&mut self, statement: &Statement<'_>) {
+        if !statement.is_word(0, "DROP") {
+            return;
+        }
+        let kind_index = if statement.is_word(1, "TEMPORARY") { 2 } else { 1 };
+        if let Some(kind) = statement.word(kind_index).filter(|kind| is_object_kind(kind)) {
+            let message = format!("DROP {} without a Down section, this migration cannot be rolled back", kind);
+            self.report(LintRule::DropWithoutDown, statement, 0, message);
+        }
+    }
+
+    /// CH003：`CREATE` 没有 `IF NOT EXISTS`（`CREATE OR REPLACE` 除外），失败后重试会报对象已存在
+    fn check_create(&mut self, statement: &Statement<'_>) {
+        if !statement.is_word(0, "CREATE") || statement.is_word(1, "OR") {
+            return;
+        }
+        let mut index = 1;
+        while statement.word(index).is_some_and(|word| matches!(word.as_str(), "TEMPORARY" | "MATERIALIZED" | "LIVE" | "WINDOW")) {
+            index += 1;
+        }
+        let Some(kind) = statement.word(index).filter(|kind| is_object_kind(kind)) else {
+            return;
+        };
+        let has_if_not_exists = statement.is_word(index + 1, "IF")
+            && statement.is_word(index + 2, "NOT")
+            && statement.is_word(index + 3, "EXISTS");
+        if !has_if_not_exists {
+            let message = format!("CREATE {} without IF NOT EXISTS is not idempotent when the migration is retried", kind);
+            self.report(LintRule::CreateWithoutIfNotExists, statement, 0, message);
+        }
+    }
+
+    /// CH004：`ALTER TABLE ... UPDATE/DELETE` 和 `DELETE FROM` 必须有 WHERE，且条件不能恒为真
+    fn check_mutation(&mut self, statement: &Statement<'_>) {
+        let depths = statement.depths();
+        let mutations: Vec<usize> = if statement.is_word(0, "DELETE") && statement.is_word(1, "FROM") {
+            vec![0]
+        } else if statement.is_word(0, "ALTER") && statement.is_word(1, "TABLE") {
+            // 子命令出现在表名（及 ON CLUSTER）之后或顶层逗号之后；TTL 中的 DELETE 不是子命令
+            (2..statement.tokens.len())
+                .filter(|&index| depths[index] == 0)
+                .filter(|&index| statement.is_word(index, "UPDATE") || statement.is_word(index, "DELETE"))
+                .filter(|&index| !statement.tokens[2..index].iter().any(|token| token.text.eq_ignore_ascii_case("TTL")))
+                .collect()
+        } else {
+            return;
+        };
+
+        for start in mutations {
+            let kind = statement.tokens[start].text.to_ascii_uppercase();
+            // 只在当前子命令内查找 WHERE：子命令到下一个「顶层逗号 + 子命令关键字」为止，
+            // `UPDATE a = 1, b = 2 WHERE ...` 中的逗号分隔的是赋值
+            let end = (start + 1..statement.tokens.len())
+                .find(|&index| {
+                    depths[index] == 0
+                        && statement.is_punct(index, ",")
+                        && statement.word(index + 1).is_some_and(|word| ALTER_SUBCOMMANDS.contains(&word.as_str()))
+                })
+                .unwrap_or(statement.tokens.len());
+            let where_index = (start + 1..end)
+                .find(|&index| depths[index] == 0 && statement.is_word(index, "WHERE"));
+            let Some(where_index) = where_index else {
+                let message = format!("{} mutation without a WHERE clause rewrites every part of the table", kind);
+                self.report(LintRule::MutationWithoutWhere, statement, start, message);
+                continue;
+            };
+
+            let condition: String = statement.tokens[where_index + 1..end].iter()
+                .map(|token| token.text.to_ascii_uppercase())
+                .collect();
+            if matches!(condition.as_str(), "1" | "TRUE" | "1=1" | "(1)" | "(TRUE)") {
+                let message = format!("{} mutation with WHERE {} affects every row of the table", kind, condition);
+                self.report(LintRule::MutationWithoutWhere, statement, where_index, message);
+            }
+        }
+    }
+}
+
+/// `ALTER TABLE` 子命令的第一个关键字
+const ALTER_SUBCOMMANDS: [&str; 17] = [
+    "ADD", "APPLY", "ATTACH", "CLEAR", "COMMENT", "DELETE", "DETACH", "DROP", "FETCH", "FREEZE",
+    "MATERIALIZE", "MODIFY", "MOVE", "REMOVE", "RENAME", "REPLACE", "UPDATE",
+];
+
+fn is_object_kind(word: &str) -> bool {
+    matches!(word, "TABLE" | "VIEW" | "DICTIONARY" | "DATABASE")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser;
+
+    fn lint(file_name: &str, content: &str) -> Vec<(String, String)> {
+        let (parsed_name, sections) = parser::parse_migration(file_name, content).unwrap();
+        let migration = MigrationFile {
+            version: parsed_name.version.clone(),
+            name: parsed_name.name.clone(),
+            file_name: file_name.to_string(),
+            up_sql: sections.up_sql,
+            down_sql: sections.down_sql,
+            checksum: String::new(),
+            is_baseline: parsed_name.is_baseline(),
+            up_source: SourceMap::new(file_name, sections.up_lines),
+            down_source: SourceMap::new(file_name, sections.down_lines),
+            code: None,
+            repeatable: parsed_name.is_repeatable(),
+            directives: sections.directives,
+        };
+        lint_migration(&migration, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .map(|issue| (issue.rule.code().to_string(), issue.location))
+            .collect()
+    }
+
+    fn issue(code: &str, location: &str) -> (String, String) {
+        (code.to_string(), location.to_string())
+    }
+
+    #[test]
+    fn ch001_varchar_length() {
+        let content = "-- +migrate Up\nCREATE TABLE IF NOT EXISTS t (\n    name VARCHAR(255),\n    code FixedString(2)\n) ENGINE = Log;\n";
+        assert_eq!(lint("V001__a.sql", content), vec![issue("CH001", "V001__a.sql:3:10")]);
+    }
+
+    #[test]
+    fn ch002_drop_without_down() {
+        let content = "-- +migrate Up\nDROP TABLE IF EXISTS old_events;\n";
+        assert_eq!(lint("V001__a.sql", content), vec![issue("CH002", "V001__a.sql:2:1")]);
+
+        let content = "-- +migrate Up\nDROP TABLE old_events;\n-- +migrate Down\nCREATE TABLE IF NOT EXISTS old_events (id UInt64) ENGINE = Log;\n";
+        assert!(lint("V001__a.sql", content).is_empty());
+    }
+
+    #[test]
+    fn ch003_create_without_if_not_exists() {
+        let content = "-- +migrate Up\nSELECT 1; CREATE MATERIALIZED VIEW mv AS SELECT 1;\nCREATE OR REPLACE VIEW v AS SELECT 1;\n";
+        assert_eq!(lint("V001__a.sql", content), vec![issue("CH003", "V001__a.sql:2:11")]);
+    }
+
+    #[test]
+    fn ch004_mutation_without_where() {
+        let content = "-- +migrate Up\nDELETE FROM events;\nALTER TABLE events UPDATE x = 1 WHERE 1;\nALTER TABLE events DELETE WHERE id = 1;\n";
+        assert_eq!(lint("V001__a.sql", content), vec![
+            issue("CH004", "V001__a.sql:2:1"),
+            issue("CH004", "V001__a.sql:3:33"),
+        ]);
+    }
+
+    #[test]
+    fn ch004_checks_each_subcommand() {
+        // 第二个子命令的 WHERE 不属于第一个 UPDATE
+        let content = "-- +migrate Up\nALTER TABLE events UPDATE x = 1, DELETE WHERE id = 1;\n";
+        assert_eq!(lint("V001__a.sql", content), vec![issue("CH004", "V001__a.sql:2:20")]);
+
+        let content = "-- +migrate Up\nALTER TABLE events UPDATE x = if(a, 1, 2) WHERE id IN (1, 2), DELETE WHERE id = 3;\n";
+        assert!(lint("V001__a.sql", content).is_empty());
+
+        // 多个赋值之间的逗号不结束 UPDATE 子命令
+        let content = "-- +migrate Up\nALTER TABLE events UPDATE a = 1, b = 2 WHERE id = 1;\n";
+        assert!(lint("V001__a.sql", content).is_empty());
+
+        let content = "-- +migrate Up\nALTER TABLE events UPDATE a = 1, b = 2, DROP COLUMN c, UPDATE d = 3 WHERE 1;\n";
+        assert_eq!(lint("V001__a.sql", content), vec![
+            issue("CH004", "V001__a.sql:2:20"),
+            issue("CH004", "V001__a.sql:2:69"),
+        ]);
+
+        let content = "-- +migrate Up\nALTER TABLE events MODIFY TTL d + INTERVAL 1 DAY DELETE;\n";
+        assert!(lint("V001__a.sql", content).is_empty());
+    }
+
+    #[test]
+    fn ch005_empty_up() {
+        let content = "-- +migrate Up\n-- TODO: 在这里添加你的迁移 SQL\n\n-- +migrate Down\n";
+        assert_eq!(lint("V002__todo.sql", content), vec![issue("CH005", "V002__todo.sql")]);
+
+        let content = "-- +lint-ignore CH005\n-- +migrate Up\n-- nothing to do\n";
+        assert!(lint("V002__todo.sql", content).is_empty());
+    }
+
+    #[test]
+    fn respects_nosplit_and_lint_ignore() {
+        let content = "-- +nosplit\n-- +lint-ignore CH003\n-- +migrate Up\nCREATE TABLE t (s VARCHAR(1)) ENGINE = Log;\nDELETE FROM t";
+        // 整段作为一条 CREATE 语句，其中的 DELETE 不是单独的语句
+        assert_eq!(lint("V001__a.sql", content), vec![issue("CH001", "V001__a.sql:4:19")]);
+    }
+}
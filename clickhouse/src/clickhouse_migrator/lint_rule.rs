@@ -0,0 +1,105 @@
+//! 静态检查规则及其级别（与 `lint` 的检查逻辑分开，供执行指令解析和构建脚本使用）
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintRule {
+    VarcharLength,
+    DropWithoutDown,
+    CreateWithoutIfNotExists,
+    MutationWithoutWhere,
+    EmptyUp,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 5] = [
+        LintRule::VarcharLength,
+        LintRule::DropWithoutDown,
+        LintRule::CreateWithoutIfNotExists,
+        LintRule::MutationWithoutWhere,
+        LintRule::EmptyUp,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            LintRule::VarcharLength => "CH001",
+            LintRule::DropWithoutDown => "CH002",
+            LintRule::CreateWithoutIfNotExists => "CH003",
+            LintRule::MutationWithoutWhere => "CH004",
+            LintRule::EmptyUp => "CH005",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LintRule::VarcharLength => "varchar-length",
+            LintRule::DropWithoutDown => "drop-without-down",
+            LintRule::CreateWithoutIfNotExists => "create-without-if-not-exists",
+            LintRule::MutationWithoutWhere => "mutation-without-where",
+            LintRule::EmptyUp => "empty-up",
+        }
+    }
+
+    pub fn default_severity(self) -> Severity {
+        match self {
+            LintRule::MutationWithoutWhere | LintRule::EmptyUp => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+
+    /// 按规则代码（`CH001`）或名称（`varchar-length`）查找，不区分大小写
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        Self::ALL.into_iter()
+            .find(|rule| rule.code().eq_ignore_ascii_case(text) || rule.name().eq_ignore_ascii_case(text))
+            .ok_or_else(|| anyhow!(
+                "Unknown lint rule '{}' (available: {})",
+                text,
+                Self::ALL.map(|rule| rule.code()).join(", ")
+            ))
+    }
+}
+
+impl Serialize for LintRule {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl std::fmt::Display for LintRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.code(), self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Off,
+    Warning,
+    Error,
+}
+
+impl std::str::FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(Severity::Off),
+            "warning" | "warn" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(anyhow!("Invalid lint severity '{}' (expected off, warning or error)", text.trim())),
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Off => write!(f, "off"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use anyhow::{Result, Context, anyhow};
+use clickhouse::Client;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{info, warn, debug, error};
+use super::cluster::ClusterConfig;
+use super::server_error;
+
+/// 锁表中唯一的锁名（同一张锁表只保护一个迁移流程）
+const LOCK_NAME: &str = "migrate";
+
+/// 写入抢占记录后等待其它并发写入落盘的时间
+const CLAIM_SETTLE_DELAY: Duration = Duration::from_millis(500);
+
+/// 锁表使用的存储引擎
+#[derive(Debug, Clone)]
+pub enum LockBackend {
+    /// ReplacingMergeTree 表：先写入抢占记录，再回读确认最早的有效记录是否属于自己
+    MergeTree,
+    /// KeeperMap 表：依赖 keeper_map_strict_mode 保证同一个 key 只能被写入一次
+    KeeperMap { root_path: String },
+}
+
+/// 迁移锁配置
+#[derive(Debug, Clone)]
+pub struct LockConfig {
+    pub backend: LockBackend,
+    /// 等待其它迁移器释放锁的最长时间
+    pub wait_timeout: Duration,
+    /// 锁的有效期，持有者在此期间没有心跳则视为过期
+    pub ttl: Duration,
+    pub heartbeat_interval: Duration,
+    pub poll_interval: Duration,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        Self {
+            backend: LockBackend::MergeTree,
+            wait_timeout: Duration::from_secs(300),
+            ttl: Duration::from_secs(60),
+            heartbeat_interval: Duration::from_secs(15),
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// 锁持有者信息
+#[derive(Debug, Clone)]
+pub struct LockInfo {
+    pub owner_id: String,
+    pub host: String,
+    pub acquired_at: String,
+    pub heartbeat_at: String,
+    pub expires_at: String,
+}
+
+impl std::fmt::Display for LockInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} on {} (acquired at {}, last heartbeat {}, expires at {})",
+            self.owner_id, self.host, self.acquired_at, self.heartbeat_at, self.expires_at
+        )
+    }
+}
+
+/// 锁表的访问封装，不持有锁本身
+#[derive(Clone)]
+pub(crate) struct LockTable {
+    client: Arc<Client>,
+    table_name: String,
+    config: LockConfig,
+    cluster: Option<ClusterConfig>,
+}
+
+/// 已获取的迁移锁，调用 `release` 释放；被丢弃时停止心跳，锁在 TTL 后自动过期
+pub(crate) struct MigrationLock {
+    table: LockTable,
+    owner_id: String,
+    heartbeat: Option<JoinHandle<anyhow::Error>>,
+}
+
+impl LockTable {
+    pub(crate) fn new(
+        client: Arc<Client>,
+        table_name: String,
+        config: LockConfig,
+        cluster: Option<ClusterConfig>,
+    ) -> Self {
+        Self { client, table_name, config, cluster }
+    }
+
+    /// 创建锁表（集群模式下在所有节点创建复制表）
+    async fn setup(&self) -> Result<()> {
+        let engine = match (&self.config.backend, &self.cluster) {
+            (LockBackend::MergeTree, None) => {
+                "ReplacingMergeTree(heartbeat_at) ORDER BY (lock_name, owner_id)".to_string()
+            }
+            (LockBackend::MergeTree, Some(cluster)) => format!(
+                "{} ORDER BY (lock_name, owner_id)",
+                cluster.replicated_engine("ReplacingMergeTree", &self.table_name, "heartbeat_at")
+            ),
+            (LockBackend::KeeperMap { root_path }, _) => format!(
+                "KeeperMap('{}/{}') PRIMARY KEY lock_name",
+                root_path.trim_end_matches('/'), self.table_name
+            ),
+        };
+        let on_cluster = match &self.cluster {
+            Some(cluster) => format!(" ON CLUSTER '{}'", cluster.name),
+            None => String::new(),
+        };
+
+        let create_sql = format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {}{} (
+                lock_name String,
+                owner_id String,
+                host String,
+                acquired_at DateTime64(3) DEFAULT now64(3),
+                heartbeat_at DateTime64(3) DEFAULT now64(3),
+                expires_at DateTime64(3),
+                released UInt8 DEFAULT 0
+            ) ENGINE = {}
+            "#,
+            self.table_name, on_cluster, engine
+        );
+
+        let mut query = self.client.query(&create_sql);
+        if let Some(cluster) = &self.cluster {
+            query = query.with_option(
+                "distributed_ddl_task_timeout",
+                cluster.ddl_task_timeout.as_secs().to_string(),
+            );
+        }
+
+        query.execute().await
+            .with_context(|| format!("Failed to create lock table {}", self.table_name))?;
+
+        debug!("Lock table {} ensured", self.table_name);
+        Ok(())
+    }
+
+    fn select_source(&self) -> String {
+        match self.config.backend {
+            LockBackend::MergeTree => format!("{} FINAL", self.table_name),
+            LockBackend::KeeperMap { .. } => self.table_name.clone(),
+        }
+    }
+
+    /// 复制锁表需要 quorum 写入 + 顺序一致读，保证抢占结果在所有副本上一致
+    fn with_consistency(&self, query: clickhouse::query::Query) -> clickhouse::query::Query {
+        match (&self.config.backend, &self.cluster) {
+            (LockBackend::MergeTree, Some(_)) => query
+                .with_option("insert_quorum", "auto")
+                .with_option("select_sequential_consistency", "1"),
+            _ => query,
+        }
+    }
+
+    /// 查询当前有效的锁持有者
+    pub(crate) async fn current_holder(&self) -> Result<Option<LockInfo>> {
+        let query = format!(
+            "SELECT owner_id, host, toString(acquired_at), toString(heartbeat_at), toString(expires_at)
+             FROM {} WHERE lock_name = ? AND released = 0 AND expires_at > now64(3)
+             ORDER BY acquired_at, owner_id LIMIT 1",
+            self.select_source()
+        );
+
+        let rows = self.with_consistency(self.client.query(&query))
+            .bind(LOCK_NAME)
+            .fetch_all::<(String, String, String, String, String)>()
+            .await
+            .context("Failed to query migration lock holder")?;
+
+        Ok(rows.into_iter().next().map(|(owner_id, host, acquired_at, heartbeat_at, expires_at)| {
+            LockInfo { owner_id, host, acquired_at, heartbeat_at, expires_at }
+        }))
+    }
+
+    /// 获取锁，在 wait_timeout 内轮询等待
+    pub(crate) async fn acquire(&self) -> Result<MigrationLock> {
+        self.setup().await?;
+
+        let owner_id = uuid::Uuid::new_v4().to_string();
+        let host = hostname::get()
+            .map(|h| h.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| "unknown".to_string());
+        let deadline = Instant::now() + self.config.wait_timeout;
+
+        info!(owner_id = %owner_id, host = %host, "Acquiring migration lock");
+
+        loop {
+            if self.try_claim(&owner_id, &host).await? {
+                info!(owner_id = %owner_id, "Migration lock acquired");
+                let heartbeat = self.spawn_heartbeat(owner_id.clone());
+                return Ok(MigrationLock {
+                    table: self.clone(),
+                    owner_id,
+                    heartbeat: Some(heartbeat),
+                });
+            }
+
+            let holder = self.current_holder().await?;
+            if Instant::now() >= deadline {
+                return Err(match holder {
+                    Some(holder) => anyhow!(
+                        "Timed out after {:?} waiting for migration lock held by {}",
+                        self.config.wait_timeout, holder
+                    ),
+                    None => anyhow!(
+                        "Timed out after {:?} waiting for migration lock",
+                        self.config.wait_timeout
+                    ),
+                });
+            }
+
+            if let Some(holder) = holder {
+                info!("Migration lock is held by {}, waiting", holder);
+            }
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// 尝试抢占一次锁
+    async fn try_claim(&self, owner_id: &str, host: &str) -> Result<bool> {
+        match self.config.backend {
+            LockBackend::MergeTree => {
+                if self.current_holder().await?.is_some() {
+                    return Ok(false);
+                }
+
+                self.insert_claim(owner_id, host, false).await?;
+                tokio::time::sleep(CLAIM_SETTLE_DELAY).await;
+
+                // 并发写入的抢占记录中，最早的一条获胜，其余的撤回自己的记录
+                match self.current_holder().await? {
+                    Some(holder) if holder.owner_id == owner_id => Ok(true),
+                    _ => {
+                        self.mark_released(owner_id).await?;
+                        Ok(false)
+                    }
+                }
+            }
+            LockBackend::KeeperMap { .. } => {
+                match self.insert_claim(owner_id, host, true).await {
+                    Ok(()) => return Ok(true),
+                    Err(e) if is_key_exists(&e) => {}
+                    Err(e) => return Err(e),
+                }
+
+                // key 已存在：如果持有者已过期，清理后下一轮重新抢占
+                if self.current_holder().await?.is_none() {
+                    warn!("Removing expired migration lock from {}", self.table_name);
+                    self.delete_expired().await?;
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    async fn insert_claim(&self, owner_id: &str, host: &str, strict: bool) -> Result<()> {
+        let insert_sql = format!(
+            "INSERT INTO {} (lock_name, owner_id, host, acquired_at, heartbeat_at, expires_at, released)
+             VALUES (?, ?, ?, now64(3), now64(3), now64(3) + toIntervalMillisecond(?), 0)",
+            self.table_name
+        );
+
+        let mut query = self.with_consistency(self.client.query(&insert_sql))
+            .bind(LOCK_NAME)
+            .bind(owner_id)
+            .bind(host)
+            .bind(self.config.ttl.as_millis() as u64);
+        if strict {
+            query = query.with_option("keeper_map_strict_mode", "1");
+        }
+
+        query.execute().await
+            .context("Failed to write migration lock claim")?;
+        Ok(())
+    }
+
+    /// 刷新心跳并延长过期时间，再回读确认锁仍属于 `owner_id`
+    ///
+    /// 返回 false 表示锁已丢失：已过期（可能已被其它迁移器获取）、被强制释放或被当作过期锁清理。
+    async fn refresh(&self, owner_id: &str) -> Result<bool> {
+        let ttl_ms = self.config.ttl.as_millis() as u64;
+
+        let query = match self.config.backend {
+            LockBackend::MergeTree => self.client.query(&format!(
+                "INSERT INTO {table} (lock_name, owner_id, host, acquired_at, heartbeat_at, expires_at, released)
+                 SELECT lock_name, owner_id, host, acquired_at, now64(3), now64(3) + toIntervalMillisecond(?), 0
+                 FROM {table} FINAL WHERE lock_name = ? AND owner_id = ? AND released = 0 AND expires_at > now64(3)",
+                table = self.table_name
+            )),
+            LockBackend::KeeperMap { .. } => self.client.query(&format!(
+                "ALTER TABLE {} UPDATE heartbeat_at = now64(3), expires_at = now64(3) + toIntervalMillisecond(?)
+                 WHERE lock_name = ? AND owner_id = ?",
+                self.table_name
+            )),
+        };
+
+        self.with_consistency(query).bind(ttl_ms).bind(LOCK_NAME).bind(owner_id)
+            .execute().await
+            .context("Failed to refresh migration lock heartbeat")?;
+
+        // 过期的记录不会被刷新，KeeperMap 的 UPDATE 在记录已被删除时也不会报错，需要回读确认
+        let holder = self.current_holder().await?;
+        Ok(holder.is_some_and(|holder| holder.owner_id == owner_id))
+    }
+
+    /// 定期刷新心跳；锁已不属于自己或超过 TTL 没有一次成功时任务结束，返回锁丢失的原因
+    fn spawn_heartbeat(&self, owner_id: String) -> JoinHandle<anyhow::Error> {
+        let table = self.clone();
+        tokio::spawn(async move {
+            let mut last_success = Instant::now();
+            loop {
+                tokio::time::sleep(table.config.heartbeat_interval).await;
+                let remaining = table.config.ttl.saturating_sub(last_success.elapsed());
+                let result = match tokio::time::timeout(remaining, table.refresh(&owner_id)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(anyhow!("Heartbeat did not complete before the lock expired")),
+                };
+
+                match result {
+                    Ok(true) => {
+                        last_success = Instant::now();
+                        debug!(owner_id = %owner_id, "Migration lock heartbeat sent");
+                    }
+                    Ok(false) => {
+                        error!(owner_id = %owner_id, "Migration lock lost, it is no longer held by this migrator");
+                        return anyhow!(
+                            "Migration lock lost: it expired, was forcibly released or was taken over by another migrator"
+                        );
+                    }
+                    Err(e) if last_success.elapsed() >= table.config.ttl => {
+                        error!(owner_id = %owner_id, "Migration lock lost, no successful heartbeat within {:?}: {}", table.config.ttl, e);
+                        return anyhow!(
+                            "Migration lock lost: no successful heartbeat within {:?}, another migrator may have taken over",
+                            table.config.ttl
+                        );
+                    }
+                    Err(e) => warn!("Migration lock heartbeat failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// 释放指定持有者的锁
+    async fn mark_released(&self, owner_id: &str) -> Result<()> {
+        let query = match self.config.backend {
+            LockBackend::MergeTree => self.client.query(&format!(
+                "INSERT INTO {table} (lock_name, owner_id, host, acquired_at, heartbeat_at, expires_at, released)
+                 SELECT lock_name, owner_id, host, acquired_at, now64(3), now64(3), 1
+                 FROM {table} FINAL WHERE lock_name = ? AND owner_id = ?",
+                table = self.table_name
+            )),
+            LockBackend::KeeperMap { .. } => self.client.query(&format!(
+                "ALTER TABLE {} DELETE WHERE lock_name = ? AND owner_id = ?",
+                self.table_name
+            )),
+        };
+
+        self.with_consistency(query).bind(LOCK_NAME).bind(owner_id)
+            .execute().await
+            .context("Failed to release migration lock")?;
+        Ok(())
+    }
+
+    async fn delete_expired(&self) -> Result<()> {
+        let delete_sql = format!(
+            "ALTER TABLE {} DELETE WHERE lock_name = ? AND expires_at <= now64(3)",
+            self.table_name
+        );
+
+        self.client.query(&delete_sql)
+            .bind(LOCK_NAME)
+            .execute().await
+            .context("Failed to remove expired migration lock")?;
+        Ok(())
+    }
+
+    /// 强制清空锁表，返回被清除的持有者
+    pub(crate) async fn force_unlock(&self) -> Result<Option<LockInfo>> {
+        self.setup().await?;
+
+        let holder = self.current_holder().await?;
+        let truncate_sql = format!("TRUNCATE TABLE IF EXISTS {}", self.table_name);
+        self.client.query(&truncate_sql).execute().await
+            .context("Failed to clear migration lock table")?;
+
+        match &holder {
+            Some(holder) => warn!("Migration lock forcibly removed from {}", holder),
+            None => info!("Migration lock table cleared, no active holder"),
+        }
+        Ok(holder)
+    }
+}
+
+impl MigrationLock {
+    /// 心跳发现锁已不属于自己或超过 TTL 没有成功时完成：其它迁移器此后可能获取锁，持有者应停止迁移
+    pub(crate) async fn lost(&mut self) -> anyhow::Error {
+        match self.heartbeat.as_mut() {
+            Some(heartbeat) => match heartbeat.await {
+                Ok(reason) => reason,
+                Err(e) => anyhow!("Migration lock lost: heartbeat task failed: {}", e),
+            },
+            None => std::future::pending().await,
+        }
+    }
+
+    /// 释放锁并停止心跳
+    pub(crate) async fn release(mut self) -> Result<()> {
+        if let Some(heartbeat) = self.heartbeat.take() {
+            heartbeat.abort();
+        }
+
+        self.table.mark_released(&self.owner_id).await?;
+        info!(owner_id = %self.owner_id, "Migration lock released");
+        Ok(())
+    }
+}
+
+impl Drop for MigrationLock {
+    fn drop(&mut self) {
+        if let Some(heartbeat) = self.heartbeat.take() {
+            heartbeat.abort();
+        }
+    }
+}
+
+/// strict 模式的 KeeperMap 写入已存在的 key：`Value for key '...' already exists`，
+/// 并发创建同一节点时为 `Node exists`
+fn is_key_exists(error: &anyhow::Error) -> bool {
+    server_error::code(error) == Some(server_error::KEEPER_EXCEPTION)
+        && error.chain().any(|cause| {
+            let message = cause.to_string();
+            message.contains("already exists") || message.contains("Node exists")
+        })
+}
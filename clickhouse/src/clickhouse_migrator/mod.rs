@@ -1,4 +1,25 @@
 pub mod simple_migrator;
+pub mod lock;
+pub mod cluster;
+pub mod plan;
+pub mod lexer;
+mod history;
+pub mod repair;
+pub mod builder;
+pub mod embedded;
+pub mod embed_build;
+pub mod code_migration;
+pub mod placeholders;
+pub mod directives;
+mod mutations;
+pub mod query_log;
+mod scaffold;
+pub mod lint;
+mod lint_rule;
+mod parser;
+pub mod schema;
+pub mod drift;
+mod server_error;
 
 pub use simple_migrator::{
     SimpleMigrator, 
@@ -6,8 +27,24 @@ pub use simple_migrator::{
     MigrationFile, 
     MigrationSummary, 
     MigrationStatus,
+    RepeatableMigrationStatus,
+    RepeatableState,
     FailedMigration
 };
+pub use lock::{LockBackend, LockConfig, LockInfo};
+pub use cluster::{ClusterConfig, DdlHostResult, DdlStatementResult};
+pub use plan::{MigrationPlan, PlannedMigration, ChecksumMismatch, ValidationError};
+pub use lexer::{SourceMap, SqlStatement};
+pub use repair::{RepairOptions, RepairReport, RepairAction};
+pub use builder::SimpleMigratorBuilder;
+pub use embedded::{EmbeddedMigrations, MigrationSource};
+pub use code_migration::{async_trait, ClickHouseMigration, CodeMigration};
+pub use placeholders::Placeholders;
+pub use directives::MigrationDirectives;
+pub use query_log::StatementStats;
+pub use lint::{LintConfig, LintIssue, LintReport, LintRule, Severity};
+pub use schema::{SchemaDump, SchemaObject, SchemaObjectKind};
+pub use drift::{DriftError, DriftReport, SchemaDrift};
 
 // 便利的重导出
 pub type Result<T> = anyhow::Result<T>;
@@ -16,10 +53,21 @@ pub type Result<T> = anyhow::Result<T>;
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 // 默认配置
+#[derive(Debug, Clone)]
 pub struct MigratorConfig {
     pub continue_on_failure: bool,
     pub validate_checksums: bool,
     pub concurrent_file_scan: bool,
+    /// 在 `ALTER TABLE ... UPDATE/DELETE/MATERIALIZE` 和 `DELETE FROM` 之后等待 mutation 完成
+    pub wait_for_mutations: bool,
+    /// 等待 mutation 的最长时间
+    pub mutation_timeout: std::time::Duration,
+    /// 静态检查规则的级别
+    pub lint: LintConfig,
+    /// 数据库结构快照文件（`schema dump` 写入，漂移检测读取）
+    pub schema_file: Option<String>,
+    /// 存在结构漂移时仍然执行迁移
+    pub allow_drift: bool,
 }
 
 impl Default for MigratorConfig {
@@ -28,6 +76,11 @@ impl Default for MigratorConfig {
             continue_on_failure: false,
             validate_checksums: true,
             concurrent_file_scan: true,
+            wait_for_mutations: true,
+            mutation_timeout: std::time::Duration::from_secs(3600),
+            lint: LintConfig::default(),
+            schema_file: None,
+            allow_drift: false,
         }
     }
 }
@@ -41,6 +94,16 @@ impl MigratorConfig {
                 .unwrap_or("true".to_string()) == "true",
             concurrent_file_scan: std::env::var("CONCURRENT_FILE_SCAN")
                 .unwrap_or("true".to_string()) == "true",
+            wait_for_mutations: std::env::var("WAIT_FOR_MUTATIONS")
+                .unwrap_or("true".to_string()) == "true",
+            mutation_timeout: std::env::var("MUTATION_TIMEOUT").ok()
+                .and_then(|value| directives::parse_duration(&value))
+                .unwrap_or(Self::default().mutation_timeout),
+            lint: LintConfig::default(),
+            schema_file: std::env::var("CLICKHOUSE_SCHEMA_FILE").ok()
+                .filter(|value| !value.trim().is_empty()),
+            allow_drift: std::env::var("ALLOW_SCHEMA_DRIFT")
+                .unwrap_or_default() == "true",
         }
     }
 }
\ No newline at end of file
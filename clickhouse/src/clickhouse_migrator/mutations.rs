@@ -0,0 +1,245 @@
+//! 等待迁移语句触发的 mutation（`ALTER TABLE ... UPDATE/DELETE/MATERIALIZE`、轻量删除 `DELETE FROM`）完成
+//!
+//! 这类语句提交后立即返回，mutation 在后台执行。迁移器在执行语句前记录目标表上已有的 mutation，
+//! 语句返回后轮询 `system.mutations`（集群模式下为 `clusterAllReplicas`），
+//! 直到该表上新出现的 mutation 全部完成、失败或超过最长等待时间。
+
+use anyhow::{anyhow, Context, Result};
+use clickhouse::{Client, Row};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+use super::lexer::{self, TokenKind};
+
+/// 轮询 `system.mutations` 的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 会产生 mutation 的 ALTER 命令关键字
+const MUTATION_KEYWORDS: [&str; 3] = ["UPDATE", "DELETE", "MATERIALIZE"];
+
+/// mutation 作用的表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MutationTarget {
+    /// 未指定数据库时为 None，使用当前数据库
+    pub database: Option<String>,
+    pub table: String,
+}
+
+impl std::fmt::Display for MutationTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.database {
+            Some(database) => write!(f, "{}.{}", database, self.table),
+            None => write!(f, "{}", self.table),
+        }
+    }
+}
+
+/// 执行语句前目标表上已有的 mutation：(节点, mutation_id)
+pub(crate) type ExistingMutations = BTreeSet<(String, String)>;
+
+#[derive(Debug, Row, Deserialize)]
+struct MutationRow {
+    host: String,
+    mutation_id: String,
+    command: String,
+    parts_to_do: i64,
+    is_done: u8,
+    latest_fail_reason: String,
+}
+
+/// 判断语句是否为会产生 mutation 的 `ALTER TABLE` 或轻量删除 `DELETE FROM`，返回作用的表
+pub(crate) fn mutation_target(sql: &str) -> Option<MutationTarget> {
+    let tokens = lexer::tokenize(sql).ok()?;
+    let mut significant = tokens.iter().filter(|token| !token.kind.is_trivia());
+
+    let is_keyword = |token: Option<&lexer::Token<'_>>, keyword: &str| {
+        token.is_some_and(|t| t.kind == TokenKind::Word && t.text.eq_ignore_ascii_case(keyword))
+    };
+    let first = significant.next();
+    let lightweight_delete = is_keyword(first, "DELETE");
+    let second_keyword = if lightweight_delete { "FROM" } else { "TABLE" };
+    if !(lightweight_delete || is_keyword(first, "ALTER")) || !is_keyword(significant.next(), second_keyword) {
+        return None;
+    }
+
+    // [database.]table，标识符可能带引号
+    let first = unquote(significant.next()?)?;
+    let mut rest = significant.peekable();
+    let (database, table) = if rest.peek().is_some_and(|t| t.text == ".") {
+        rest.next();
+        (Some(first), unquote(rest.next()?)?)
+    } else {
+        (None, first)
+    };
+
+    if lightweight_delete {
+        return Some(MutationTarget { database, table });
+    }
+
+    let is_mutation = rest.any(|token| {
+        token.kind == TokenKind::Word
+            && MUTATION_KEYWORDS.iter().any(|keyword| token.text.eq_ignore_ascii_case(keyword))
+    });
+
+    is_mutation.then_some(MutationTarget { database, table })
+}
+
+fn unquote(token: &lexer::Token<'_>) -> Option<String> {
+    match token.kind {
+        TokenKind::Word => Some(token.text.to_string()),
+        TokenKind::QuotedIdentifier => Some(token.text[1..token.text.len() - 1].to_string()),
+        _ => None,
+    }
+}
+
+/// 目标表上已有的 mutation，在执行语句前获取，用于找出该语句创建的 mutation
+pub(crate) async fn existing_mutations(
+    client: &Client,
+    target: &MutationTarget,
+    cluster: Option<&str>,
+) -> Result<ExistingMutations> {
+    let query = format!(
+        "SELECT hostName() AS host, mutation_id FROM {} WHERE database = {} AND table = ?",
+        mutations_source(cluster),
+        database_filter(target)
+    );
+
+    let mut request = client.query(&query);
+    if let Some(database) = &target.database {
+        request = request.bind(database);
+    }
+    let rows = request
+        .bind(&target.table)
+        .fetch_all::<(String, String)>()
+        .await
+        .with_context(|| format!("Failed to query system.mutations for {}", target))?;
+    Ok(rows.into_iter().collect())
+}
+
+/// 等待目标表上不在 `existing` 中的 mutation 全部完成
+///
+/// 任一 mutation 的 `latest_fail_reason` 不为空时立即返回错误；超过 `timeout` 仍未完成时返回错误。
+pub(crate) async fn wait_for_mutations(
+    client: &Client,
+    target: &MutationTarget,
+    cluster: Option<&str>,
+    existing: &ExistingMutations,
+    timeout: Duration,
+) -> Result<()> {
+    let query = format!(
+        "SELECT hostName() AS host, mutation_id, command, toInt64(parts_to_do) AS parts_to_do, is_done, latest_fail_reason
+         FROM {}
+         WHERE database = {} AND table = ?
+         ORDER BY create_time, host",
+        mutations_source(cluster),
+        database_filter(target)
+    );
+
+    let deadline = Instant::now() + timeout;
+    let mut last_parts_to_do = None;
+
+    loop {
+        let mut request = client.query(&query);
+        if let Some(database) = &target.database {
+            request = request.bind(database);
+        }
+        let mutations: Vec<MutationRow> = request
+            .bind(&target.table)
+            .fetch_all::<MutationRow>()
+            .await
+            .with_context(|| format!("Failed to query system.mutations for {}", target))?
+            .into_iter()
+            .filter(|m| !existing.contains(&(m.host.clone(), m.mutation_id.clone())))
+            .collect();
+
+        if mutations.is_empty() {
+            debug!("No mutations found for {}", target);
+            return Ok(());
+        }
+
+        if let Some(failed) = mutations.iter().find(|m| !m.latest_fail_reason.is_empty()) {
+            return Err(anyhow!(
+                "Mutation {} on {} failed: {}\nCommand: {}",
+                failed.mutation_id, target, failed.latest_fail_reason, failed.command
+            ));
+        }
+
+        let running: Vec<&MutationRow> = mutations.iter().filter(|m| m.is_done == 0).collect();
+        if running.is_empty() {
+            info!(table = %target, mutations = mutations.len(), "Mutations completed");
+            return Ok(());
+        }
+
+        let parts_to_do: i64 = running.iter().map(|m| m.parts_to_do).sum();
+        if last_parts_to_do != Some(parts_to_do) {
+            info!(table = %target, mutations = running.len(), parts_to_do, "Waiting for mutations");
+            last_parts_to_do = Some(parts_to_do);
+        }
+
+        if Instant::now() >= deadline {
+            let ids: Vec<&str> = running.iter().map(|m| m.mutation_id.as_str()).collect();
+            return Err(anyhow!(
+                "Mutations on {} still running after {:?} (parts_to_do: {}): {}",
+                target, timeout, parts_to_do, ids.join(", ")
+            ));
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// 集群模式下查询所有副本的 mutation
+fn mutations_source(cluster: Option<&str>) -> String {
+    match cluster {
+        Some(cluster) => format!(
+            "clusterAllReplicas('{}', system.mutations)",
+            cluster.replace('\\', "\\\\").replace('\'', "\\'")
+        ),
+        None => "system.mutations".to_string(),
+    }
+}
+
+fn database_filter(target: &MutationTarget) -> &'static str {
+    if target.database.is_some() { "?" } else { "currentDatabase()" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(database: Option<&str>, table: &str) -> Option<MutationTarget> {
+        Some(MutationTarget { database: database.map(str::to_string), table: table.to_string() })
+    }
+
+    #[test]
+    fn detects_alter_mutations() {
+        assert_eq!(mutation_target("ALTER TABLE events UPDATE x = 1 WHERE id = 2"), target(None, "events"));
+        assert_eq!(
+            mutation_target("alter table `db`.\"events\" ON CLUSTER c DELETE WHERE 1"),
+            target(Some("db"), "events")
+        );
+        assert_eq!(
+            mutation_target("ALTER TABLE events MATERIALIZE INDEX idx"),
+            target(None, "events")
+        );
+        assert_eq!(mutation_target("ALTER TABLE events ADD COLUMN x UInt8"), None);
+    }
+
+    #[test]
+    fn detects_lightweight_delete() {
+        assert_eq!(mutation_target("DELETE FROM db.events WHERE id = 1"), target(Some("db"), "events"));
+        assert_eq!(mutation_target("-- cleanup\ndelete from events where 1"), target(None, "events"));
+        assert_eq!(mutation_target("SELECT * FROM events"), None);
+        assert_eq!(mutation_target("DELETE events"), None);
+    }
+
+    #[test]
+    fn queries_all_replicas_on_cluster() {
+        assert_eq!(mutations_source(None), "system.mutations");
+        assert_eq!(
+            mutations_source(Some("main")),
+            "clusterAllReplicas('main', system.mutations)"
+        );
+    }
+}
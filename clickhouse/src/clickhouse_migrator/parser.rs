@@ -0,0 +1,228 @@
+//! 迁移文件的解析：文件名（版本号、描述）和内容（Up/Down 部分、执行指令）
+//!
+//! 运行时扫描迁移目录和构建脚本嵌入迁移文件时使用同一套解析，
+//! 嵌入的迁移文件在编译期就能发现内容错误。本文件只依赖 anyhow、serde 和同目录下的
+//! `lexer`、`directives`、`lint_rule`、`placeholders` 模块，可以被 `build.rs` 直接引用。
+
+use anyhow::{anyhow, Context, Result};
+use super::directives::{self, MigrationDirectives};
+use super::lexer::{self, SourceMap};
+use super::placeholders;
+
+/// 可重复迁移（`R__<名称>.sql`）在历史表中的版本前缀
+const REPEATABLE_PREFIX: &str = "R__";
+
+/// 基线迁移的版本：只记录到历史表，不执行 SQL
+const BASELINE_VERSION: &str = "000";
+
+/// 从文件名 `V<数字>__<描述>.sql` 或 `R__<描述>.sql` 解析出的信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MigrationFileName {
+    /// 历史表中的版本：版本化迁移为文件名中的数字（保留前导零），可重复迁移为 `R__<描述>`
+    pub version: String,
+    /// 版本号数值，可重复迁移为 None
+    pub number: Option<u32>,
+    /// 描述，下划线替换为空格
+    pub name: String,
+}
+
+impl MigrationFileName {
+    pub fn is_repeatable(&self) -> bool {
+        self.number.is_none()
+    }
+
+    /// 版本为 V000；Up 部分为空的版本化迁移同样作为基线迁移（见 `SimpleMigrator`）
+    pub fn is_baseline(&self) -> bool {
+        self.version == BASELINE_VERSION
+    }
+}
+
+/// 解析迁移文件名（可以带或不带 `.sql` 后缀）
+pub(crate) fn parse_file_name(file_name: &str) -> Result<MigrationFileName> {
+    let stem = file_name.strip_suffix(".sql").unwrap_or(file_name);
+    let format_error = || anyhow!(
+        "Invalid migration filename format: {} (expected: V001__description.sql or R__description.sql)",
+        file_name
+    );
+
+    if let Some(description) = stem.strip_prefix(REPEATABLE_PREFIX) {
+        if description.is_empty() {
+            return Err(format_error());
+        }
+        return Ok(MigrationFileName {
+            version: stem.to_string(),
+            number: None,
+            name: description.replace('_', " "),
+        });
+    }
+
+    let (digits, description) = stem.strip_prefix('V')
+        .and_then(|rest| rest.split_once("__"))
+        .ok_or_else(format_error)?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) || description.is_empty() {
+        return Err(format_error());
+    }
+    let number = digits.parse::<u32>()
+        .map_err(|_| anyhow!("Migration version {} in {} is out of range (maximum: {})", digits, file_name, u32::MAX))?;
+
+    Ok(MigrationFileName {
+        version: digits.to_string(),
+        number: Some(number),
+        name: description.replace('_', " "),
+    })
+}
+
+/// 迁移文件解析出的 Up/Down SQL 及其每一行在文件中的行号
+#[derive(Debug)]
+pub(crate) struct SqlSections {
+    pub up_sql: String,
+    pub down_sql: Option<String>,
+    pub up_lines: Vec<(usize, usize)>,
+    pub down_lines: Vec<(usize, usize)>,
+    pub directives: MigrationDirectives,
+}
+
+#[derive(Debug)]
+enum Section {
+    Up,
+    Down,
+}
+
+/// 解析SQL内容（分离UP和DOWN部分），同时记录每一行在文件中的行号
+pub(crate) fn parse_sql_content(content: &str) -> Result<SqlSections> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut up_sql = String::new();
+    let mut down_sql: Option<String> = None;
+    let mut up_lines = Vec::new();
+    let mut down_lines = Vec::new();
+    let mut current_section = Section::Up;
+    let mut directives = MigrationDirectives::default();
+
+    for (line_index, line) in lines.into_iter().enumerate() {
+        let trimmed = line.trim();
+
+        if trimmed == "-- +migrate Up" {
+            current_section = Section::Up;
+            continue;
+        } else if trimmed == "-- +migrate Down" {
+            current_section = Section::Down;
+            down_sql = Some(String::new());
+            continue;
+        }
+
+        // 执行指令作用于整个文件；指令行保留在 SQL 中（是注释），因此也计入校验和
+        directives.parse_line(trimmed)
+            .with_context(|| format!("line {}", line_index + 1))?;
+
+        // 静态检查指令不影响执行，不计入 SQL 和校验和，已应用的迁移也可以添加
+        if directives::is_lint_directive(trimmed) {
+            continue;
+        }
+
+        // 跳过元数据注释
+        if trimmed.starts_with("-- ") && !trimmed.contains("/*") && !trimmed.contains("--") {
+            continue;
+        }
+
+        match current_section {
+            Section::Up => {
+                up_sql.push_str(line);
+                up_sql.push('\n');
+                up_lines.push(line_index + 1);
+            }
+            Section::Down => {
+                if let Some(ref mut down) = down_sql {
+                    down.push_str(line);
+                    down.push('\n');
+                    down_lines.push(line_index + 1);
+                }
+            }
+        }
+    }
+
+    let (up_sql, up_lines) = trim_with_lines(&up_sql, &up_lines);
+    let (down_sql, down_lines) = match down_sql {
+        Some(down) => {
+            let (down, lines) = trim_with_lines(&down, &down_lines);
+            (Some(down), lines)
+        }
+        None => (None, Vec::new()),
+    };
+
+    Ok(SqlSections { up_sql, down_sql, up_lines, down_lines, directives })
+}
+
+/// 解析迁移文件：文件名、Up/Down 部分和执行指令，并检查 SQL 的词法和占位符格式
+pub(crate) fn parse_migration(file_name: &str, content: &str) -> Result<(MigrationFileName, SqlSections)> {
+    let parsed_name = parse_file_name(file_name)?;
+    let sections = parse_sql_content(content)?;
+
+    validate_sql(&sections.up_sql, &SourceMap::new(file_name, sections.up_lines.clone()))?;
+    if let Some(down_sql) = &sections.down_sql {
+        validate_sql(down_sql, &SourceMap::new(file_name, sections.down_lines.clone()))?;
+    }
+
+    Ok((parsed_name, sections))
+}
+
+/// 检查一段 SQL 能否按 ClickHouse 词法分析，以及占位符格式是否正确（不检查占位符是否有值）
+fn validate_sql(sql: &str, source: &SourceMap) -> Result<()> {
+    lexer::tokenize(sql)
+        .map_err(|e| anyhow!("{} at {}", e.message, source.locate(e.line, e.column)))?;
+    placeholders::check_syntax(sql)
+        .with_context(|| format!("Invalid placeholder in {}", source.file_name()))
+}
+
+/// 去掉首尾空白，并同步调整每一行对应的 (文件行号, 列偏移)
+fn trim_with_lines(text: &str, file_lines: &[usize]) -> (String, Vec<(usize, usize)>) {
+    let trimmed_start = text.trim_start();
+    let prefix = &text[..text.len() - trimmed_start.len()];
+    let skipped_lines = prefix.matches('\n').count();
+    let first_column_offset = prefix.rsplit('\n').next().unwrap_or("").chars().count();
+
+    let lines = file_lines.iter()
+        .skip(skipped_lines)
+        .enumerate()
+        .map(|(i, &line)| (line, if i == 0 { first_column_offset } else { 0 }))
+        .collect();
+
+    (trimmed_start.trim_end().to_string(), lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_versioned_and_repeatable_file_names() {
+        let parsed = parse_file_name("V007__add_user_email.sql").unwrap();
+        assert_eq!(parsed, MigrationFileName {
+            version: "007".to_string(),
+            number: Some(7),
+            name: "add user email".to_string(),
+        });
+
+        let parsed = parse_file_name("R__refresh_views.sql").unwrap();
+        assert_eq!(parsed.version, "R__refresh_views");
+        assert!(parsed.is_repeatable());
+    }
+
+    #[test]
+    fn rejects_invalid_file_names() {
+        for file_name in ["V__x.sql", "V1x__a.sql", "V001_a.sql", "V001__.sql", "R__.sql", "001__a.sql"] {
+            assert!(parse_file_name(file_name).is_err(), "{}", file_name);
+        }
+
+        let error = parse_file_name("V4294967296__too_big.sql").unwrap_err().to_string();
+        assert!(error.contains("out of range"), "{}", error);
+    }
+
+    #[test]
+    fn reports_content_errors_with_file_location() {
+        let error = parse_migration("V001__a.sql", "-- +migrate Up\nSELECT 1;\nSELECT 'x;\n").unwrap_err();
+        assert_eq!(error.to_string(), "Unterminated string literal at V001__a.sql:3:8");
+
+        let error = parse_migration("V001__a.sql", "-- +timout 30m\nSELECT 1;\n").unwrap_err();
+        assert_eq!(format!("{:#}", error), "line 1: Unknown directive: -- +timout 30m");
+    }
+}
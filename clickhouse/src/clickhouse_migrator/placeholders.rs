@@ -0,0 +1,234 @@
+//! 迁移 SQL 中的 `${name}` 占位符替换
+//!
+//! 取值优先级（后者覆盖前者）：内置变量 → 配置文件 → 环境变量 `CLICKHOUSE_PLACEHOLDERS_<NAME>`。
+//! 校验和基于替换前的文本计算，不同环境之间的校验和保持一致。
+//! 注释中的占位符不替换，`${{` 输出字面的 `${`。
+
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use super::lexer::{self, TokenKind};
+
+/// 环境变量占位符前缀，`CLICKHOUSE_PLACEHOLDERS_TTL_DAYS` 对应 `${ttl_days}`
+pub const ENV_PREFIX: &str = "CLICKHOUSE_PLACEHOLDERS_";
+
+#[derive(Debug, Clone, Default)]
+pub struct Placeholders {
+    values: BTreeMap<String, String>,
+}
+
+impl Placeholders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置占位符的值，已存在时覆盖
+    pub fn insert(&mut self, name: &str, value: &str) {
+        self.values.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn extend(&mut self, values: &BTreeMap<String, String>) {
+        for (name, value) in values {
+            self.insert(name, value);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    pub fn values(&self) -> &BTreeMap<String, String> {
+        &self.values
+    }
+
+    /// 替换 SQL 中的所有占位符，存在未定义或格式错误的占位符时返回错误
+    ///
+    /// 注释中的内容原样保留；字符串和引用标识符中只替换格式正确的 `${name}`，其余 `${` 原样保留。
+    /// `${{` 是转义，输出字面的 `${`。
+    pub fn expand(&self, sql: &str) -> Result<String> {
+        let expansion = self.expand_tokens(sql)?;
+        if !expansion.unresolved.is_empty() {
+            let names: Vec<String> = expansion.unresolved.iter().map(|name| format!("${{{}}}", name)).collect();
+            return Err(anyhow!(
+                "Unresolved placeholder(s): {} (defined: {:?})",
+                names.join(", "),
+                self.values.keys().collect::<Vec<_>>()
+            ));
+        }
+
+        Ok(expansion.result)
+    }
+
+    fn expand_tokens(&self, sql: &str) -> Result<Expansion<'_>> {
+        let tokens = lexer::tokenize(sql)
+            .map_err(|e| anyhow!("Failed to parse SQL for placeholder expansion: {}", e))?;
+        let mut expansion = Expansion {
+            values: &self.values,
+            result: String::with_capacity(sql.len()),
+            unresolved: Vec::new(),
+        };
+
+        // 连续的关键字、标识符和标点作为一段替换，占位符 `${a.b}` 会被切成多个 token
+        let mut code_start = None;
+        for token in &tokens {
+            let is_code = !matches!(
+                token.kind,
+                TokenKind::LineComment | TokenKind::BlockComment
+                    | TokenKind::StringLiteral | TokenKind::QuotedIdentifier | TokenKind::Heredoc
+            );
+            if is_code {
+                code_start.get_or_insert(token.offset);
+                continue;
+            }
+
+            if let Some(start) = code_start.take() {
+                expansion.expand(&sql[start..token.offset], false)?;
+            }
+            if token.kind.is_trivia() {
+                expansion.result.push_str(token.text);
+            } else {
+                expansion.expand(token.text, true)?;
+            }
+        }
+        if let Some(start) = code_start {
+            expansion.expand(&sql[start..], false)?;
+        }
+
+        Ok(expansion)
+    }
+}
+
+struct Expansion<'a> {
+    values: &'a BTreeMap<String, String>,
+    result: String,
+    unresolved: Vec<String>,
+}
+
+impl Expansion<'_> {
+    /// 替换一段文本中的占位符；`literal` 为 true 时（字符串、引用标识符）格式不正确的 `${` 原样保留
+    fn expand(&mut self, text: &str, literal: bool) -> Result<()> {
+        let mut rest = text;
+
+        while let Some(start) = rest.find("${") {
+            self.result.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+
+            if let Some(escaped) = after.strip_prefix('{') {
+                self.result.push_str("${");
+                rest = escaped;
+                continue;
+            }
+
+            let name = after.find('}').map(|end| &after[..end]);
+            match name {
+                Some(name) if is_valid_name(name) => {
+                    match self.values.get(name) {
+                        Some(value) => self.result.push_str(value),
+                        None => {
+                            if !self.unresolved.iter().any(|unresolved| unresolved == name) {
+                                self.unresolved.push(name.to_string());
+                            }
+                        }
+                    }
+                    rest = &after[name.len() + 1..];
+                }
+                _ if literal => {
+                    self.result.push_str("${");
+                    rest = after;
+                }
+                Some(name) => return Err(anyhow!("Invalid placeholder name: ${{{}}}", name)),
+                None => return Err(anyhow!("Unterminated placeholder: {}", truncate(&rest[start..]))),
+            }
+        }
+
+        self.result.push_str(rest);
+        Ok(())
+    }
+}
+
+/// 占位符名称：字母或下划线开头，由字母、数字、下划线和 `.` 组成
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// 只检查占位符格式，不要求占位符有值（构建脚本在编译期检查嵌入的迁移文件时使用）
+pub(crate) fn check_syntax(sql: &str) -> Result<()> {
+    Placeholders::new().expand_tokens(sql).map(|_| ())
+}
+
+/// 从 `CLICKHOUSE_PLACEHOLDERS_<NAME>` 环境变量读取占位符，名称转为小写
+pub fn from_env() -> BTreeMap<String, String> {
+    std::env::vars()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(ENV_PREFIX)
+                .filter(|name| !name.is_empty())
+                .map(|name| (name.to_lowercase(), value))
+        })
+        .collect()
+}
+
+fn truncate(text: &str) -> String {
+    text.chars().take(40).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders() -> Placeholders {
+        let mut placeholders = Placeholders::new();
+        placeholders.insert("cluster", "main");
+        placeholders.insert("ttl_days", "30");
+        placeholders.insert("db.name", "analytics");
+        placeholders
+    }
+
+    #[test]
+    fn expands_in_code_strings_and_identifiers() {
+        let sql = "CREATE TABLE ${db.name}.events ON CLUSTER '${cluster}' (id UInt64) TTL d + INTERVAL ${ttl_days} DAY";
+        assert_eq!(
+            placeholders().expand(sql).unwrap(),
+            "CREATE TABLE analytics.events ON CLUSTER 'main' (id UInt64) TTL d + INTERVAL 30 DAY"
+        );
+        assert_eq!(placeholders().expand("SELECT * FROM `${cluster}_t`").unwrap(), "SELECT * FROM `main_t`");
+    }
+
+    #[test]
+    fn leaves_comments_untouched() {
+        let sql = "-- see ${docs}\nSELECT 1 /* ${also_not_defined */";
+        assert_eq!(placeholders().expand(sql).unwrap(), sql);
+    }
+
+    #[test]
+    fn keeps_malformed_placeholders_in_string_literals() {
+        assert_eq!(placeholders().expand("SELECT '${', '${not valid}'").unwrap(), "SELECT '${', '${not valid}'");
+    }
+
+    #[test]
+    fn supports_escape() {
+        assert_eq!(placeholders().expand("SELECT '${{cluster}', 1").unwrap(), "SELECT '${cluster}', 1");
+        assert_eq!(placeholders().expand("SELECT ${{x}").unwrap(), "SELECT ${x}");
+    }
+
+    #[test]
+    fn reports_unresolved_and_malformed_placeholders() {
+        let error = placeholders().expand("SELECT ${a}, '${b}', ${a}").unwrap_err().to_string();
+        assert!(error.starts_with("Unresolved placeholder(s): ${a}, ${b}"), "{}", error);
+
+        let error = placeholders().expand("SELECT ${cluster").unwrap_err().to_string();
+        assert!(error.starts_with("Unterminated placeholder"), "{}", error);
+
+        let error = placeholders().expand("SELECT ${1x}").unwrap_err().to_string();
+        assert_eq!(error, "Invalid placeholder name: ${1x}");
+    }
+
+    #[test]
+    fn validates_names() {
+        assert!(is_valid_name("ttl_days"));
+        assert!(is_valid_name("_db.name"));
+        assert!(!is_valid_name("1x"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("a-b"));
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Serialize;
+use super::directives::MigrationDirectives;
+
+/// 迁移计划：`migrate()` 将要执行的内容，生成计划时不会执行任何 SQL
+#[derive(Debug, Default, Serialize)]
+pub struct MigrationPlan {
+    /// 按执行顺序排列的待执行迁移
+    pub pending: Vec<PlannedMigration>,
+    /// 需要（重新）执行的可重复迁移，在所有版本化迁移之后执行
+    pub repeatable: Vec<PlannedMigration>,
+    pub checksum_mismatches: Vec<ChecksumMismatch>,
+    /// 已应用但迁移目录中找不到文件的版本
+    pub missing_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedMigration {
+    pub version: String,
+    pub name: String,
+    pub checksum: String,
+    /// 基线迁移只记录版本，不执行 SQL
+    pub is_baseline: bool,
+    pub statements: Vec<String>,
+    pub directives: MigrationDirectives,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChecksumMismatch {
+    pub version: String,
+    pub stored_checksum: String,
+    pub file_checksum: String,
+}
+
+/// 已应用迁移的校验和与迁移文件不一致，`migrate()` 拒绝执行
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationError {
+    pub mismatches: Vec<ChecksumMismatch>,
+}
+
+impl MigrationPlan {
+    /// 计划能否被 `migrate()` 执行（校验和不一致时 `migrate()` 会拒绝执行）
+    pub fn is_executable(&self) -> bool {
+        self.checksum_mismatches.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.repeatable.is_empty()
+    }
+
+    pub fn total_statements(&self) -> usize {
+        self.pending.iter()
+            .chain(&self.repeatable)
+            .map(|m| m.statements.len())
+            .sum()
+    }
+}
+
+impl std::fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Migration {}: checksum mismatch (stored: {}, file: {})",
+            self.version, self.stored_checksum, self.file_checksum
+        )
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Migration validation failed:")?;
+        for mismatch in &self.mismatches {
+            write!(f, "\n{}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+fn write_statements(f: &mut std::fmt::Formatter<'_>, migration: &PlannedMigration) -> std::fmt::Result {
+    if !migration.directives.is_empty() {
+        writeln!(f, "    directives: {}", migration.directives)?;
+    }
+    for (i, statement) in migration.statements.iter().enumerate() {
+        writeln!(f, "    [{}] {}", i + 1, statement.replace('\n', "\n        "))?;
+    }
+    Ok(())
+}
+
+impl std::fmt::Display for MigrationPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Migration Plan:")?;
+        writeln!(f, "  Pending migrations: {}", self.pending.len())?;
+        writeln!(f, "  Statements to execute: {}", self.total_statements())?;
+
+        for migration in &self.pending {
+            if migration.is_baseline {
+                writeln!(f, "\n  V{} - {} (baseline, SQL skipped)", migration.version, migration.name)?;
+                continue;
+            }
+
+            writeln!(f, "\n  V{} - {} ({} statements)", migration.version, migration.name, migration.statements.len())?;
+            write_statements(f, migration)?;
+        }
+
+        if !self.repeatable.is_empty() {
+            writeln!(f, "\n  Repeatable migrations to apply: {}", self.repeatable.len())?;
+            for migration in &self.repeatable {
+                writeln!(f, "\n  {} ({} statements)", migration.version, migration.statements.len())?;
+                write_statements(f, migration)?;
+            }
+        }
+
+        if !self.checksum_mismatches.is_empty() {
+            writeln!(f, "\nChecksum mismatches (migrate will refuse to run):")?;
+            for mismatch in &self.checksum_mismatches {
+                writeln!(f, "  - {}", mismatch)?;
+            }
+        }
+
+        if !self.missing_files.is_empty() {
+            writeln!(f, "\nWarnings:")?;
+            for version in &self.missing_files {
+                writeln!(f, "  - Applied migration {} not found in migration files", version)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+//! 迁移语句的 query_id 和 `system.query_log` 中的执行统计
+//!
+//! 每条迁移语句使用确定的 query_id：`<service>-<version>-<语句序号>-<run_id>`，
+//! 可以直接在 `system.query_log` 中找到某次迁移运行的所有语句。
+//! 重试时每次尝试使用新的 query_id（加上 `-<尝试序号>` 后缀），历史记录中保存最后一次尝试的 query_id。
+
+use anyhow::{Context, Result};
+use clickhouse::{Client, Row};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// 迁移中第 `statement_index` 条语句（从 1 开始）的 query_id
+pub(crate) fn query_id(service_name: &str, version: &str, statement_index: usize, run_id: &str) -> String {
+    format!("{}-{}-{}-{}", service_name, version, statement_index, run_id)
+}
+
+/// 第 `attempt` 次尝试（从 1 开始）的 query_id；第一次尝试使用原 query_id
+///
+/// 超时的查询在服务器上可能仍在运行，重试使用相同的 query_id 会失败（QUERY_WITH_SAME_ID_IS_ALREADY_RUNNING）。
+pub(crate) fn attempt_query_id(query_id: &str, attempt: u32) -> String {
+    if attempt <= 1 {
+        query_id.to_string()
+    } else {
+        format!("{}-{}", query_id, attempt)
+    }
+}
+
+/// 单条迁移语句的执行统计
+#[derive(Debug, Clone, Serialize)]
+pub struct StatementStats {
+    pub version: String,
+    pub statement_index: usize,
+    pub query_id: String,
+    pub read_rows: u64,
+    pub read_bytes: u64,
+    /// 峰值内存（字节）
+    pub memory_usage: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Row, Deserialize)]
+struct QueryLogRow {
+    query_id: String,
+    read_rows: u64,
+    read_bytes: u64,
+    memory_usage: u64,
+    query_duration_ms: u64,
+}
+
+/// 查询 `system.query_log` 中这些语句的统计，按 `query_ids` 的顺序返回
+///
+/// 查询前执行 `SYSTEM FLUSH LOGS`（没有权限时忽略）；在日志中找不到的语句不会出现在结果中。
+/// 重试过的语句传入最后一次尝试的 query_id（见 `attempt_query_id`），即取最后一次执行的统计。
+pub(crate) async fn fetch_statement_stats(
+    client: &Client,
+    version: &str,
+    query_ids: &[(usize, String)],
+) -> Result<Vec<StatementStats>> {
+    if query_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if let Err(e) = client.query("SYSTEM FLUSH LOGS").execute().await {
+        debug!("Failed to flush system logs, query_log may be incomplete: {}", e);
+    }
+
+    let ids: Vec<&str> = query_ids.iter().map(|(_, id)| id.as_str()).collect();
+    let rows = client
+        .query(
+            "SELECT query_id,
+                    argMax(read_rows, event_time_microseconds) AS read_rows,
+                    argMax(read_bytes, event_time_microseconds) AS read_bytes,
+                    argMax(memory_usage, event_time_microseconds) AS memory_usage,
+                    argMax(query_duration_ms, event_time_microseconds) AS query_duration_ms
+             FROM system.query_log
+             WHERE event_date >= yesterday() AND type != 'QueryStart' AND has(?, query_id)
+             GROUP BY query_id",
+        )
+        .bind(ids)
+        .fetch_all::<QueryLogRow>()
+        .await
+        .context("Failed to query system.query_log")?;
+
+    Ok(query_ids
+        .iter()
+        .filter_map(|(statement_index, id)| {
+            rows.iter().find(|row| row.query_id == *id).map(|row| StatementStats {
+                version: version.to_string(),
+                statement_index: *statement_index,
+                query_id: row.query_id.clone(),
+                read_rows: row.read_rows,
+                read_bytes: row.read_bytes,
+                memory_usage: row.memory_usage,
+                duration_ms: row.query_duration_ms,
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,87 @@
+use serde::Serialize;
+use super::plan::ChecksumMismatch;
+
+/// 修复选项
+#[derive(Debug, Clone, Default)]
+pub struct RepairOptions {
+    /// 确认过修改的版本，这些版本的已存储校验和会被更新为文件当前的校验和
+    pub acknowledged_versions: Vec<String>,
+    /// 确认所有校验和不一致的版本
+    pub acknowledge_all: bool,
+    /// 为 false 时只生成预览，不修改历史表
+    pub confirm: bool,
+}
+
+/// 修复对历史表所做（或预览中将要做）的一项修改
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RepairAction {
+    UpdateChecksum {
+        version: String,
+        old_checksum: String,
+        new_checksum: String,
+    },
+    /// 删除之后已成功的版本的失败尝试记录
+    PurgeFailedAttempts {
+        version: String,
+        attempts: Vec<u32>,
+    },
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RepairReport {
+    pub actions: Vec<RepairAction>,
+    /// 未被确认、仍会阻止迁移的校验和不一致
+    pub unacknowledged_mismatches: Vec<ChecksumMismatch>,
+    /// 修改是否已写入历史表（预览时为 false）
+    pub applied: bool,
+}
+
+impl RepairReport {
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+impl std::fmt::Display for RepairAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepairAction::UpdateChecksum { version, old_checksum, new_checksum } => write!(
+                f,
+                "Migration {}: update checksum {} -> {}",
+                version, old_checksum, new_checksum
+            ),
+            RepairAction::PurgeFailedAttempts { version, attempts } => write!(
+                f,
+                "Migration {}: purge {} failed attempt(s) {:?}",
+                version, attempts.len(), attempts
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.applied {
+            writeln!(f, "Repair applied:")?;
+        } else {
+            writeln!(f, "Repair preview (nothing changed, confirm to apply):")?;
+        }
+
+        if self.actions.is_empty() {
+            writeln!(f, "  Nothing to repair")?;
+        }
+        for action in &self.actions {
+            writeln!(f, "  - {}", action)?;
+        }
+
+        if !self.unacknowledged_mismatches.is_empty() {
+            writeln!(f, "\nUnacknowledged checksum mismatches (still blocking migrate):")?;
+            for mismatch in &self.unacknowledged_mismatches {
+                writeln!(f, "  - {}", mismatch)?;
+            }
+        }
+
+        Ok(())
+    }
+}
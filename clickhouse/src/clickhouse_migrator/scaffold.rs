@@ -0,0 +1,69 @@
+//! 新迁移文件的命名和模板
+
+/// 版本号的最小位数
+pub(crate) const MIN_VERSION_WIDTH: usize = 3;
+
+/// 名称转为文件名中使用的描述：小写 ASCII 字母、数字和下划线
+///
+/// 其它字符（包括非 ASCII 字符）替换为下划线并合并，全部被替换时使用 `migration`。
+pub(crate) fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+
+    let slug = slug.trim_end_matches('_');
+    if slug.is_empty() {
+        "migration".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// 新迁移文件的内容；`name` 与文件名中的描述不同（如包含非 ASCII 字符）时保留在注释中
+///
+/// Up 部分只有 TODO 注释，未编辑时 `lint` 报告 CH005（empty-up）。
+pub(crate) fn template(file_name: &str, name: &str, slug: &str) -> String {
+    let mut content = format!("-- {}\n", file_name);
+    if name.trim() != slug {
+        content.push_str(&format!("-- {}\n", name.trim()));
+    }
+    content.push_str(
+        "\n-- +migrate Up\n-- TODO: 在这里添加你的迁移 SQL\n\n\n-- +migrate Down\n-- TODO: 在这里添加回滚 SQL\n",
+    );
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_keeps_lowercase_ascii_words() {
+        assert_eq!(slugify("Add User Roles table"), "add_user_roles_table");
+        assert_eq!(slugify("add_user-email  v2"), "add_user_email_v2");
+        assert_eq!(slugify("  --drop tmp--  "), "drop_tmp");
+    }
+
+    #[test]
+    fn slugify_replaces_non_ascii() {
+        assert_eq!(slugify("新增 phone 列"), "phone");
+        assert_eq!(slugify("新增用户电话列"), "migration");
+        assert_eq!(slugify(""), "migration");
+    }
+
+    #[test]
+    fn template_keeps_original_name() {
+        let content = template("V007__phone.sql", "新增 phone 列", "phone");
+        assert!(content.starts_with("-- V007__phone.sql\n-- 新增 phone 列\n"));
+        assert!(content.contains("-- +migrate Up\n"));
+        assert!(content.contains("-- +migrate Down\n"));
+
+        let content = template("V007__phone.sql", "phone", "phone");
+        assert!(!content.contains("-- phone\n"));
+    }
+}
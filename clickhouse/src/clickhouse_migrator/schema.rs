@@ -0,0 +1,157 @@
+//! 数据库结构快照：迁移后数据库中所有表、字典和视图的建表语句
+//!
+//! 快照是确定的：对象按类型和名称排序，去掉当前数据库名前缀和行尾空白，
+//! 同一结构在不同环境（不同数据库名）中生成相同的 `schema.sql`。
+
+use anyhow::{Context, Result};
+use clickhouse::{Client, Row};
+use serde::{Deserialize, Serialize};
+use super::lexer::{self, TokenKind};
+
+/// 快照文件开头的说明
+const HEADER: &str = "-- 此文件由 `schema dump` 生成，请勿手动修改\n-- 迁移后数据库中的表、字典和视图（不含迁移历史表）\n";
+
+/// 对象类型，快照中按此顺序排列（视图依赖表和字典）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaObjectKind {
+    Table,
+    Dictionary,
+    View,
+    MaterializedView,
+}
+
+impl SchemaObjectKind {
+    pub(crate) fn from_engine(engine: &str) -> Self {
+        match engine {
+            "Dictionary" => SchemaObjectKind::Dictionary,
+            "MaterializedView" => SchemaObjectKind::MaterializedView,
+            "View" | "LiveView" | "WindowView" => SchemaObjectKind::View,
+            _ => SchemaObjectKind::Table,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaObject {
+    pub name: String,
+    pub kind: SchemaObjectKind,
+    pub engine: String,
+    /// 规范化后的建表语句（不含末尾分号）
+    pub create_query: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchemaDump {
+    pub objects: Vec<SchemaObject>,
+}
+
+impl SchemaDump {
+    /// 快照文件内容
+    pub fn to_sql(&self) -> String {
+        let mut sql = HEADER.to_string();
+        for object in &self.objects {
+            sql.push('\n');
+            sql.push_str(&object.create_query);
+            sql.push_str(";\n");
+        }
+        sql
+    }
+}
+
+#[derive(Debug, Row, Deserialize)]
+pub(crate) struct TableRow {
+    pub name: String,
+    pub engine: String,
+}
+
+/// 当前数据库中的所有对象，`excluded` 中的表（迁移历史表、历史表升级备份、锁表）除外
+///
+/// 物化视图的内部表（`.inner.*`）和临时表不包含在内。
+pub(crate) async fn list_objects(client: &Client, excluded: &[String]) -> Result<Vec<TableRow>> {
+    client
+        .query(
+            "SELECT name, engine FROM system.tables
+             WHERE database = currentDatabase() AND NOT is_temporary
+               AND NOT startsWith(name, '.inner') AND NOT has(?, name)
+             ORDER BY name",
+        )
+        .bind(excluded)
+        .fetch_all::<TableRow>()
+        .await
+        .context("Failed to list tables from system.tables")
+}
+
+/// 读取当前数据库中所有对象的定义
+pub(crate) async fn dump(client: &Client, database: &str, excluded: &[String]) -> Result<SchemaDump> {
+    let tables = list_objects(client, excluded).await?;
+
+    let mut objects = Vec::with_capacity(tables.len());
+    for table in tables {
+        let kind = SchemaObjectKind::from_engine(&table.engine);
+        let statement = match kind {
+            SchemaObjectKind::Dictionary => "SHOW CREATE DICTIONARY",
+            _ => "SHOW CREATE TABLE",
+        };
+        let create_query = client
+            .query(&format!("{} {}", statement, quote_identifier(&table.name)))
+            .fetch_one::<String>()
+            .await
+            .with_context(|| format!("Failed to get definition of {}", table.name))?;
+
+        objects.push(SchemaObject {
+            create_query: normalize(&create_query, database),
+            name: table.name,
+            kind,
+            engine: table.engine,
+        });
+    }
+
+    objects.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+    Ok(SchemaDump { objects })
+}
+
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+/// 去掉 `database.` 前缀（字符串和注释中的除外）、行尾空白和末尾分号
+fn normalize(create_query: &str, database: &str) -> String {
+    let is_database = |text: &str| {
+        text == database || text.strip_prefix('`').and_then(|t| t.strip_suffix('`')) == Some(database)
+    };
+
+    let without_database = match lexer::tokenize(create_query) {
+        Ok(tokens) => {
+            let mut sql = String::with_capacity(create_query.len());
+            let mut skip_dot = false;
+            for (index, token) in tokens.iter().enumerate() {
+                if skip_dot {
+                    skip_dot = false;
+                    continue;
+                }
+                let qualifies = matches!(token.kind, TokenKind::Word | TokenKind::QuotedIdentifier)
+                    && is_database(token.text)
+                    && tokens.get(index + 1).is_some_and(|next| next.kind == TokenKind::Punct && next.text == ".");
+                if qualifies {
+                    skip_dot = true;
+                } else {
+                    sql.push_str(token.text);
+                }
+            }
+            sql
+        }
+        // 服务器返回的语句不应无法解析；万一如此，保留原文
+        Err(_) => create_query.to_string(),
+    };
+
+    without_database
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .trim_end_matches(';')
+        .trim_end()
+        .to_string()
+}
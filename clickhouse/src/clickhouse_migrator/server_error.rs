@@ -0,0 +1,73 @@
+//! 区分 ClickHouse 服务器异常（`Code: N. DB::Exception: ...`）和客户端错误
+
+use clickhouse::error::Error;
+
+/// KeeperMap 写入已存在的 key、Keeper 节点已存在等 Keeper 相关异常
+pub(crate) const KEEPER_EXCEPTION: u32 = 999;
+
+/// 服务器端的超时和网络异常：TIMEOUT_EXCEEDED、TOO_MANY_SIMULTANEOUS_QUERIES、SOCKET_TIMEOUT、NETWORK_ERROR
+const TRANSIENT_CODES: [u32; 4] = [159, 202, 209, 210];
+
+/// 错误链中的 clickhouse 客户端错误
+pub(crate) fn client_error(error: &anyhow::Error) -> Option<&Error> {
+    error.chain().find_map(|cause| cause.downcast_ref::<Error>())
+}
+
+/// 服务器异常的错误码；网络错误、超时等客户端错误返回 None
+pub(crate) fn code(error: &anyhow::Error) -> Option<u32> {
+    match client_error(error)? {
+        Error::BadResponse(message) => parse_code(message),
+        _ => None,
+    }
+}
+
+/// 可以重试的错误：网络错误、超时，以及没有错误码的 HTTP 错误（通常来自代理）
+///
+/// SYNTAX_ERROR、UNKNOWN_TABLE 等服务器异常重试也不会成功，返回 false。
+pub(crate) fn is_transient(error: &anyhow::Error) -> bool {
+    match client_error(error) {
+        Some(Error::Network(_)) | Some(Error::TimedOut) => true,
+        Some(Error::BadResponse(message)) => parse_code(message).is_none_or(|code| TRANSIENT_CODES.contains(&code)),
+        _ => false,
+    }
+}
+
+fn parse_code(message: &str) -> Option<u32> {
+    let rest = &message[message.find("Code: ")? + "Code: ".len()..];
+    let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_exception_code() {
+        let message = "Code: 999. DB::Exception: Value for key 'migrate' already exists. (KEEPER_EXCEPTION) (version 24.8.1.1)";
+        assert_eq!(parse_code(message), Some(999));
+        assert_eq!(parse_code("Code: 62. DB::Exception: Syntax error"), Some(62));
+        assert_eq!(parse_code("502 Bad Gateway"), None);
+    }
+
+    #[test]
+    fn client_errors_have_no_code() {
+        let error = anyhow::Error::new(Error::TimedOut).context("Failed to execute statement");
+        assert!(matches!(client_error(&error), Some(Error::TimedOut)));
+        assert_eq!(code(&error), None);
+
+        let error = anyhow::Error::new(Error::BadResponse("Code: 60. DB::Exception: Unknown table".into()));
+        assert_eq!(code(&error), Some(60));
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        let bad_response = |message: &str| anyhow::Error::new(Error::BadResponse(message.to_string()));
+        assert!(is_transient(&anyhow::Error::new(Error::TimedOut).context("Statement timed out")));
+        assert!(is_transient(&bad_response("Code: 159. DB::Exception: Timeout exceeded")));
+        assert!(is_transient(&bad_response("502 Bad Gateway")));
+        assert!(!is_transient(&bad_response("Code: 62. DB::Exception: Syntax error")));
+        assert!(!is_transient(&bad_response("Code: 60. DB::Exception: Unknown table")));
+        assert!(!is_transient(&anyhow::anyhow!("Failed to parse host results")));
+    }
+}
@@ -1,60 +1,162 @@
 use anyhow::{Result, Context, anyhow};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use std::time::Instant;
 use tokio::task::JoinSet;
 use tracing::{info, warn, error, debug};
 use sha2::{Sha256, Digest};
+use chrono::{DateTime, Utc};
+use clickhouse::Row;
 use crate::database::ClickHouseConnectionManager;
+use super::lock::{LockConfig, LockInfo, LockTable};
+use super::cluster::{self, ClusterConfig, DdlStatementResult};
+use super::plan::{MigrationPlan, PlannedMigration, ChecksumMismatch, ValidationError};
+use super::lexer::{self, SourceMap, SqlStatement};
+use super::history::{self, RunContext};
+use super::repair::{RepairOptions, RepairReport, RepairAction};
+use super::builder::SimpleMigratorBuilder;
+use super::embedded::MigrationSource;
+use super::code_migration::{ClickHouseMigration, CodeMigration};
+use super::placeholders::{self, Placeholders};
+use super::directives::MigrationDirectives;
+use super::lint::{self, LintReport};
+use super::schema::{self, SchemaDump};
+use super::drift::{self, DriftError, DriftReport};
+use super::mutations;
+use super::query_log::{self, StatementStats};
+use super::scaffold;
+use super::server_error;
+use super::parser;
+use super::MigratorConfig;
+
+/// 只选择版本化迁移记录的查询条件
+const VERSIONED_ONLY: &str = "NOT startsWith(version, 'R__')";
+/// 执行过程中写入的检查点记录的 note，执行结束后会被最终记录替换
+const CHECKPOINT_NOTE: &str = "in progress";
 
 pub struct SimpleMigrator {
     connection_manager: ClickHouseConnectionManager,
     service_name: String,
-    migrations_path: String,
+    source: MigrationSource,
+    code_migrations: Vec<CodeMigration>,
+    placeholders: Placeholders,
+    history_table: String,
+    config: MigratorConfig,
+    lock_config: LockConfig,
+    cluster: Option<ClusterConfig>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 迁移历史表中的一行：某个版本的一次执行尝试
+#[derive(Debug, Clone, Row, Serialize, Deserialize)]
 pub struct MigrationRecord {
+    /// 同一次 `migrate()` 调用产生的记录共享同一个 run_id
+    pub run_id: String,
     pub version: String,
+    /// 该版本的第几次执行尝试（从 1 开始）
+    pub attempt: u32,
     pub name: String,
-    pub applied_at: String,
-    pub execution_time_ms: u64,
     pub checksum: String,
     pub success: bool,
     pub error_message: String,
+    pub execution_time_ms: u64,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub started_at: DateTime<Utc>,
+    #[serde(with = "clickhouse::serde::chrono::datetime64::millis")]
+    pub finished_at: DateTime<Utc>,
+    pub hostname: String,
+    pub os_user: String,
+    pub tool_version: String,
+    pub statements_total: u32,
+    /// 执行结束时已成功完成的语句数
+    pub statements_completed: u32,
+    /// 附加说明（如 baseline 的描述）
+    pub note: String,
+    /// 执行的语句的 query_id，可用于在 `system.query_log` 中查找
+    pub query_ids: Vec<String>,
+    /// 以下统计来自 `system.query_log`：读取行数和字节数、峰值内存为各语句之和/最大值
+    pub read_rows: u64,
+    pub read_bytes: u64,
+    pub memory_usage: u64,
+    /// 各语句在服务器上的执行时间之和
+    pub query_duration_ms: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct MigrationFile {
     pub version: String,
     pub name: String,
+    pub file_name: String,
     pub up_sql: String,
     pub down_sql: Option<String>,
     pub checksum: String,
     pub is_baseline: bool,
+    /// Up/Down 部分每一行在迁移文件中的位置，用于错误定位
+    pub up_source: SourceMap,
+    pub down_source: SourceMap,
+    /// Rust 代码迁移（此时 up_sql 为空，file_name 为 `rust:<name>`）
+    pub code: Option<CodeMigration>,
+    /// 可重复迁移：version 为 `R__<名称>`，校验和变化时重新执行
+    pub repeatable: bool,
+    /// 文件中的 `-- +setting`、`-- +timeout`、`-- +nosplit`、`-- +retry`、`-- +lint-ignore` 指令
+    pub directives: MigrationDirectives,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct MigrationSummary {
     pub successful: Vec<MigrationRecord>,
     pub failed: Vec<FailedMigration>,
+    #[serde(rename = "total_time_ms", serialize_with = "serialize_duration_ms")]
     pub total_time: std::time::Duration,
+    /// 集群模式下每条 ON CLUSTER 语句在各节点的执行结果
+    pub ddl_results: Vec<DdlStatementResult>,
+    /// 每条执行过的语句在 `system.query_log` 中的统计
+    pub statement_stats: Vec<StatementStats>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct FailedMigration {
     pub version: String,
     pub name: String,
     pub error: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct MigrationStatus {
     pub service_name: String,
     pub migrations_table: String,
+    /// 已应用的版本化迁移数
     pub total_migrations: usize,
     pub table_exists: bool,
     pub last_migration: Option<String>,
+    /// 可重复迁移的状态，按名称排序
+    pub repeatable: Vec<RepeatableMigrationStatus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepeatableMigrationStatus {
+    pub name: String,
+    pub file_name: String,
+    pub state: RepeatableState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepeatableState {
+    /// 从未成功执行
+    Pending,
+    /// 文件在上次执行后被修改，下次迁移时会重新执行
+    Outdated,
+    UpToDate,
+}
+
+fn serialize_duration_ms<S: serde::Serializer>(duration: &std::time::Duration, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+/// 扫描得到的迁移：版本化迁移（含代码迁移）和按名称排序的可重复迁移
+struct ScannedMigrations {
+    versioned: BTreeMap<String, MigrationFile>,
+    repeatable: Vec<MigrationFile>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -64,6 +166,13 @@ pub struct MigrationVersion {
 }
 
 impl MigrationVersion {
+    /// 解析用户输入的目标版本，允许带 `V` 前缀（如 `V003`、`3`）
+    fn parse_target(version_str: &str) -> Result<Self> {
+        let trimmed = version_str.trim();
+        let number_part = trimmed.strip_prefix(['V', 'v']).unwrap_or(trimmed);
+        Self::parse(number_part)
+    }
+    
     fn parse(version_str: &str) -> Result<Self> {
         let number = version_str.parse::<u32>()
             .with_context(|| format!("Invalid version number: {}", version_str))?;
@@ -82,19 +191,46 @@ impl MigrationFile {
 }
 
 impl SimpleMigrator {
+    /// 使用默认连接参数创建迁移器，用户名和密码从 `CLICKHOUSE_USER`、`CLICKHOUSE_PASSWORD` 读取，
+    /// 迁移配置和占位符从环境变量读取（见 `MigratorConfig::from_env`）
     pub async fn new(database_url: &str, service_name: &str, migrations_path: &str) -> Result<Self> {
-        let connection_manager = ClickHouseConnectionManager::new(
-            database_url, 
-            "default", 
-            "default", 
-            "ClickHouse@123"
-        )?;
-        
-        let migrator = Self {
-            connection_manager,
-            service_name: service_name.to_string(),
-            migrations_path: migrations_path.to_string(),
-        };
+        Self::builder_from_env(database_url, service_name, migrations_path)
+            .build()
+            .await
+    }
+    
+    /// 创建集群模式的迁移器：迁移记录表使用 ReplicatedMergeTree ON CLUSTER 创建
+    pub async fn new_on_cluster(
+        database_url: &str, 
+        service_name: &str, 
+        migrations_path: &str,
+        cluster: ClusterConfig
+    ) -> Result<Self> {
+        Self::builder_from_env(database_url, service_name, migrations_path)
+            .cluster(cluster)
+            .build()
+            .await
+    }
+    
+    fn builder_from_env(database_url: &str, service_name: &str, migrations_path: &str) -> SimpleMigratorBuilder {
+        let env_or = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+        
+        Self::builder(service_name)
+            .url(database_url)
+            .user(&env_or("CLICKHOUSE_USER", "default"))
+            .password(&env_or("CLICKHOUSE_PASSWORD", ""))
+            .migrations_path(migrations_path)
+            .config(MigratorConfig::from_env())
+            .placeholders(&placeholders::from_env())
+    }
+    
+    /// 创建迁移器构建器
+    pub fn builder(service_name: &str) -> SimpleMigratorBuilder {
+        SimpleMigratorBuilder::new(service_name)
+    }
+    
+    pub(super) async fn from_builder(builder: SimpleMigratorBuilder) -> Result<Self> {
+        let migrator = Self::from_builder_offline(builder)?;
         
         // 创建迁移记录表
         migrator.setup_migrations_table().await?;
@@ -102,9 +238,82 @@ impl SimpleMigrator {
         Ok(migrator)
     }
     
+    /// 创建迁移器，不访问数据库
+    pub(super) fn from_builder_offline(builder: SimpleMigratorBuilder) -> Result<Self> {
+        let connection_manager = match builder.connection_manager {
+            Some(connection_manager) => connection_manager,
+            None => ClickHouseConnectionManager::new(
+                &builder.url,
+                &builder.database,
+                &builder.user,
+                &builder.password,
+            )?,
+        };
+        
+        let history_table = builder.history_table
+            .unwrap_or_else(|| format!("_migrations_{}", builder.service_name));
+        
+        // 内置占位符，可被显式设置的值覆盖
+        let mut placeholders = Placeholders::new();
+        placeholders.insert("database", connection_manager.database());
+        placeholders.insert("service", &builder.service_name);
+        if let Some(cluster) = &builder.cluster {
+            placeholders.insert("cluster", &cluster.name);
+        }
+        placeholders.extend(&builder.placeholders);
+        
+        Ok(Self {
+            connection_manager,
+            service_name: builder.service_name,
+            source: builder.source,
+            code_migrations: builder.code_migrations,
+            placeholders,
+            history_table,
+            config: builder.config,
+            lock_config: builder.lock_config,
+            cluster: builder.cluster,
+        })
+    }
+    
+    /// 注册 Rust 代码迁移
+    pub fn with_migration(mut self, migration: impl ClickHouseMigration + 'static) -> Self {
+        self.code_migrations.push(CodeMigration::new(migration));
+        self
+    }
+    
+    /// 设置迁移锁配置
+    pub fn with_lock_config(mut self, lock_config: LockConfig) -> Self {
+        self.lock_config = lock_config;
+        self
+    }
+    
     /// 获取迁移表名
     fn get_migration_table_name(&self) -> String {
-        format!("_migrations_{}", self.service_name)
+        self.history_table.clone()
+    }
+    
+    /// 获取迁移锁表名
+    fn get_lock_table_name(&self) -> String {
+        format!("{}_lock", self.get_migration_table_name())
+    }
+    
+    /// 历史表升级时保留的旧表名
+    fn get_history_backup_table_name(&self) -> String {
+        format!("{}_v1_backup", self.get_migration_table_name())
+    }
+    
+    /// 迁移器自己使用的表（历史表、升级备份表、锁表），不属于数据库结构
+    fn internal_table_names(&self) -> [String; 3] {
+        [self.get_migration_table_name(), self.get_history_backup_table_name(), self.get_lock_table_name()]
+    }
+    
+    fn lock_table(&self) -> LockTable {
+        LockTable::new(
+            self.connection_manager.get_client(),
+            self.get_lock_table_name(),
+            self.lock_config.clone(),
+            self.cluster.clone(),
+        )
     }
     
     /// 执行查询并返回单个值
@@ -147,14 +356,16 @@ impl SimpleMigrator {
         Ok(result)
     }
     
-    /// 执行查询并返回迁移记录元组
-    async fn query_migration_records(&self, query: &str) -> Result<Vec<(String, String, String, u64, String, u8, String)>> {
+    /// 执行查询并返回迁移记录（查询中用 `?fields` 选择 MigrationRecord 的列，`?` 依次绑定 `params`）
+    async fn query_migration_records(&self, query: &str, params: &[&str]) -> Result<Vec<MigrationRecord>> {
         debug!("Executing migration records query: {}", query);
         
-        let result = self.connection_manager.get_client()
-            .query(query)
-            .fetch_all::<(String, String, String, u64, String, u8, String)>()
-            .await?;
+        let mut request = self.connection_manager.get_client().query(query);
+        for param in params {
+            request = request.bind(*param);
+        }
+        
+        let result = request.fetch_all::<MigrationRecord>().await?;
         
         Ok(result)
     }
@@ -199,6 +410,53 @@ impl SimpleMigrator {
         }
     }
     
+    /// 执行分布式DDL，等待所有节点完成并返回各节点结果
+    async fn execute_on_cluster(
+        &self, 
+        query: &str, 
+        directives: &MigrationDirectives,
+        query_id: Option<&str>
+    ) -> Result<Vec<cluster::DdlHostResult>> {
+        let trimmed_query = query.trim();
+        debug!("Executing distributed DDL: {}", trimmed_query);
+        
+        let mut request = self.connection_manager.get_client()
+            .query(trimmed_query)
+            .with_option("distributed_ddl_output_mode", "throw");
+        if let Some(query_id) = query_id {
+            request = request.with_option("query_id", query_id);
+        }
+        if let Some(cluster) = &self.cluster {
+            request = request.with_option(
+                "distributed_ddl_task_timeout",
+                cluster.ddl_task_timeout.as_secs().to_string(),
+            );
+        }
+        // 指令中的设置在最后附加，可覆盖上面的默认值
+        for (name, value) in &directives.settings {
+            request = request.with_option(name, value);
+        }
+        
+        let output = match request.fetch_bytes("JSONEachRow") {
+            Ok(mut cursor) => cursor.collect().await,
+            Err(e) => Err(e),
+        };
+        
+        match output {
+            Ok(output) => {
+                let hosts = cluster::parse_host_results(&output)?;
+                debug!("Distributed DDL finished on {} hosts", hosts.len());
+                Ok(hosts)
+            }
+            Err(e) => {
+                let error_msg = format!("Failed to execute distributed DDL: {}\nSQL: {}", 
+                    e, trimmed_query);
+                error!("{}", error_msg);
+                Err(anyhow::Error::new(e).context(error_msg))
+            }
+        }
+    }
+    
     /// 检查表是否存在
     async fn table_exists(&self, table_name: &str) -> Result<bool> {
         let query = format!(
@@ -219,46 +477,184 @@ impl SimpleMigrator {
         }
     }
     
-    /// 创建迁移记录表
+    /// 执行迁移器自身的表结构DDL（集群模式下在所有节点执行）
+    async fn execute_schema_ddl(&self, sql: &str) -> Result<()> {
+        if self.cluster.is_some() {
+            self.execute_on_cluster(sql, &MigrationDirectives::default(), None).await?;
+        } else {
+            self.execute_ddl(sql).await?;
+        }
+        Ok(())
+    }
+    
+    /// 集群模式下的 ON CLUSTER 子句
+    fn on_cluster_clause(&self) -> String {
+        match &self.cluster {
+            Some(cluster) => format!(" ON CLUSTER '{}'", cluster.name),
+            None => String::new(),
+        }
+    }
+    
+    /// 历史表引擎（集群模式下在所有节点上复制同一份数据）
+    fn history_table_engine(&self, table_name: &str) -> String {
+        match &self.cluster {
+            Some(cluster) => cluster.replicated_engine("ReplacingMergeTree", table_name, "finished_at"),
+            None => "ReplacingMergeTree(finished_at)".to_string(),
+        }
+    }
+    
+    /// 创建迁移记录表，旧表结构会被原地升级
     async fn setup_migrations_table(&self) -> Result<()> {
         let table_name = self.get_migration_table_name();
         
-        let create_sql = format!(
-            r#"
-            CREATE TABLE IF NOT EXISTS {table_name} (
-                version String,
-                name String,
-                applied_at DateTime64(3) DEFAULT now64(3),
-                execution_time_ms UInt64,
-                checksum String,
-                success UInt8,
-                error_message String DEFAULT ''
-            ) ENGINE = MergeTree()
-            ORDER BY version
-            SETTINGS index_granularity = 8192
-            "#
+        let create_sql = history::create_table_sql(
+            &table_name, 
+            &self.on_cluster_clause(), 
+            &self.history_table_engine(&table_name)
         );
         
-        self.execute_ddl(&create_sql).await
+        self.execute_schema_ddl(&create_sql).await
             .context("Failed to create migrations table")?;
         
+        if self.history_needs_upgrade().await? {
+            self.with_migration_lock(|| async {
+                // 等锁期间可能已被其它迁移器升级
+                if self.history_needs_upgrade().await? {
+                    self.upgrade_history_table().await?;
+                }
+                Ok(())
+            }).await
+            .context("Failed to upgrade migrations table")?;
+        }
+        
+        self.add_missing_history_columns().await
+            .context("Failed to add columns to migrations table")?;
+        
         debug!("Migration table {} ensured", table_name);
         Ok(())
     }
     
-    /// 主要入口：运行待处理的迁移
+    /// 历史表是否仍为旧表结构（每次保存一行，没有 run_id 等列）
+    async fn history_needs_upgrade(&self) -> Result<bool> {
+        let table_name = self.get_migration_table_name();
+        
+        let count = self.connection_manager.get_client()
+            .query("SELECT count() FROM system.columns WHERE database = currentDatabase() AND table = ? AND name = ?")
+            .bind(table_name.as_str())
+            .bind(history::SCHEMA_MARKER_COLUMN)
+            .fetch_one::<u64>()
+            .await
+            .context("Failed to inspect migrations table columns")?;
+        
+        Ok(count == 0 && self.table_exists(&table_name).await?)
+    }
+    
+    /// 为较早创建的历史表补齐之后新增的列
+    async fn add_missing_history_columns(&self) -> Result<()> {
+        let table_name = self.get_migration_table_name();
+        let existing = self.connection_manager.get_client()
+            .query("SELECT name FROM system.columns WHERE database = currentDatabase() AND table = ?")
+            .bind(table_name.as_str())
+            .fetch_all::<String>()
+            .await
+            .context("Failed to inspect migrations table columns")?;
+        
+        for (column, definition) in history::ADDED_COLUMNS {
+            if !existing.iter().any(|name| name == column) {
+                info!("Adding column {} to migrations table {}", column, table_name);
+                self.execute_schema_ddl(&format!(
+                    "ALTER TABLE {}{} ADD COLUMN IF NOT EXISTS {} {}",
+                    table_name, self.on_cluster_clause(), column, definition
+                )).await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 将旧表结构的历史表升级为按尝试记录的新表结构，旧表保留为 `<table>_v1_backup`
+    async fn upgrade_history_table(&self) -> Result<()> {
+        let table_name = self.get_migration_table_name();
+        let new_table = format!("{}_v2", table_name);
+        let backup_table = self.get_history_backup_table_name();
+        let on_cluster = self.on_cluster_clause();
+        
+        info!("Upgrading migrations table {} to attempt-level history", table_name);
+        
+        // 清理上次升级失败留下的中间表
+        self.execute_schema_ddl(&format!("DROP TABLE IF EXISTS {}{} SYNC", new_table, on_cluster)).await?;
+        
+        self.execute_schema_ddl(&history::create_table_sql(
+            &new_table, 
+            &on_cluster, 
+            &self.history_table_engine(&new_table)
+        )).await?;
+        
+        self.execute_ddl(&history::copy_legacy_rows_sql(&table_name, &new_table)).await
+            .context("Failed to copy legacy migration records")?;
+        
+        self.execute_schema_ddl(&format!(
+            "RENAME TABLE {table_name} TO {backup_table}, {new_table} TO {table_name}{on_cluster}"
+        )).await?;
+        
+        info!("Migrations table upgraded, previous table kept as {}", backup_table);
+        Ok(())
+    }
+    
+    /// 在持有迁移锁期间执行操作，结束后（无论成功失败）释放锁
+    ///
+    /// 心跳超过锁的 TTL 没有成功时中止操作并返回错误，避免和之后获取锁的迁移器同时执行。
+    async fn with_migration_lock<T, F, Fut>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut lock = self.lock_table().acquire().await
+            .context("Failed to acquire migration lock")?;
+        
+        let result = tokio::select! {
+            result = operation() => result,
+            lost = lock.lost() => Err(lost),
+        };
+        
+        if let Err(e) = lock.release().await {
+            warn!("Failed to release migration lock, it will expire after its TTL: {}", e);
+        }
+        
+        result
+    }
+    
+    /// 主要入口：运行待处理的迁移（持有迁移锁期间执行）
     pub async fn migrate(&self) -> Result<MigrationSummary> {
+        self.with_migration_lock(|| self.migrate_locked(None, false)).await
+    }
+    
+    /// 只执行版本号不大于 `target_version` 的待处理迁移
+    pub async fn migrate_to(&self, target_version: &str) -> Result<MigrationSummary> {
+        let target = MigrationVersion::parse_target(target_version)?;
+        self.with_migration_lock(|| self.migrate_locked(Some(target), false)).await
+    }
+    
+    /// 与 `migrate()` 相同，但部分语句已执行成功的失败迁移从第一条未完成的语句继续执行
+    ///
+    /// 迁移文件在失败后被修改（校验和变化）时拒绝继续，不执行任何迁移。
+    pub async fn resume(&self) -> Result<MigrationSummary> {
+        self.with_migration_lock(|| self.migrate_locked(None, true)).await
+    }
+    
+    async fn migrate_locked(&self, target: Option<MigrationVersion>, resume: bool) -> Result<MigrationSummary> {
         let _span = tracing::info_span!("migrate", service = %self.service_name).entered();
         info!("Starting migration");
         
         let start_time = Instant::now();
         
         // 1. 扫描迁移文件
-        let migration_files = self.scan_migration_files().await
+        let scanned = self.scan_all_migrations().await
             .context("Failed to scan migration files")?;
+        let migration_files = scanned.versioned;
         
-        if migration_files.is_empty() {
-            warn!("No migration files found in {}", self.migrations_path);
+        if migration_files.is_empty() && scanned.repeatable.is_empty() {
+            warn!("No migration files found in {}", self.source);
             return Ok(MigrationSummary::no_migrations());
         }
         
@@ -271,18 +667,70 @@ impl SimpleMigrator {
             .context("Failed to get applied versions")?;
         
         // 4. 确定待执行的迁移
-        let pending = self.get_pending_migrations(&migration_files, &applied_versions)?;
+        let mut pending = self.get_pending_migrations(&migration_files, &applied_versions)?;
+        
+        // 结构快照是所有迁移执行后的结构，指定目标版本时目标之后的迁移修改的表也不参与漂移检测
+        let mut affected_tables = drift::tables_affected_by(&pending);
+        
+        if let Some(target) = &target {
+            let target_exists = migration_files.values()
+                .any(|m| m.version().map(|v| v.number == target.number).unwrap_or(false));
+            if !target_exists {
+                return Err(anyhow!("Target version {} not found in migration files", target.original));
+            }
+            
+            pending.retain(|m| m.version().map(|v| v.number <= target.number).unwrap_or(false));
+            info!("Migrating up to target version {}", target.original);
+        }
+        
+        // 5. 确定需要（重新）执行的可重复迁移；指定目标版本时不执行
+        let repeatable = if target.is_some() {
+            if !scanned.repeatable.is_empty() {
+                info!("Target version given, skipping repeatable migrations");
+            }
+            Vec::new()
+        } else {
+            self.get_pending_repeatables(scanned.repeatable).await
+                .context("Failed to check repeatable migrations")?
+        };
+        
+        // 6. 检查结构漂移（没有待执行的迁移时也检查）
+        if let Some(tables) = &mut affected_tables {
+            match drift::tables_affected_by(&repeatable) {
+                Some(repeatable_tables) => tables.extend(repeatable_tables),
+                None => affected_tables = None,
+            }
+        }
+        self.check_drift_before_migrate(affected_tables).await?;
         
-        if pending.is_empty() {
+        if pending.is_empty() && repeatable.is_empty() {
             info!("No pending migrations found");
             return Ok(MigrationSummary::no_migrations());
         }
         
-        info!("Found {} pending migrations", pending.len());
+        info!("Found {} pending migrations and {} repeatable migrations to apply", pending.len(), repeatable.len());
         self.log_pending_migrations(&pending);
+        self.log_pending_migrations(&repeatable);
         
-        // 5. 执行迁移
-        let mut summary = self.execute_pending_migrations(pending).await?;
+        // 7. 部分执行的失败迁移：resume 模式下确定继续执行的位置，否则从头执行
+        let resume_points = self.get_resume_points(&pending, resume).await?;
+        
+        // 8. 执行迁移，可重复迁移在所有版本化迁移之后执行
+        let run = RunContext::new();
+        info!(run_id = %run.run_id, "Starting migration run");
+        let mut summary = self.execute_pending_migrations(pending, &resume_points, &run).await?;
+        
+        if !repeatable.is_empty() {
+            if summary.has_failures() && !self.should_continue_on_failure() {
+                warn!("Skipping repeatable migrations because a versioned migration failed");
+            } else {
+                let repeatable_summary = self.execute_pending_migrations(repeatable, &HashMap::new(), &run).await?;
+                summary.successful.extend(repeatable_summary.successful);
+                summary.failed.extend(repeatable_summary.failed);
+                summary.ddl_results.extend(repeatable_summary.ddl_results);
+                summary.statement_stats.extend(repeatable_summary.statement_stats);
+            }
+        }
         summary.total_time = start_time.elapsed();
         
         info!(
@@ -295,55 +743,243 @@ impl SimpleMigrator {
         Ok(summary)
     }
     
-    /// 扫描迁移文件目录（并发处理）
+    /// 生成迁移计划（dry-run）：列出 `migrate()` 将执行的内容，不执行任何 SQL
+    pub async fn plan(&self) -> Result<MigrationPlan> {
+        let scanned = self.scan_all_migrations().await
+            .context("Failed to scan migration files")?;
+        let migration_files = scanned.versioned;
+        
+        if migration_files.is_empty() && scanned.repeatable.is_empty() {
+            warn!("No migration files found in {}", self.source);
+            return Ok(MigrationPlan::default());
+        }
+        
+        let (mut checksum_mismatches, missing_files) = self.check_applied_checksums(&migration_files).await?;
+        if !self.config.validate_checksums {
+            checksum_mismatches.clear();
+        }
+        
+        let applied_versions = self.get_applied_versions().await
+            .context("Failed to get applied versions")?;
+        
+        let pending = self.get_pending_migrations(&migration_files, &applied_versions)?
+            .into_iter()
+            .map(|migration| self.plan_migration(migration))
+            .collect::<Result<Vec<_>>>()?;
+        
+        let repeatable = self.get_pending_repeatables(scanned.repeatable).await
+            .context("Failed to check repeatable migrations")?
+            .into_iter()
+            .map(|migration| self.plan_migration(migration))
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(MigrationPlan {
+            pending,
+            repeatable,
+            checksum_mismatches,
+            missing_files,
+        })
+    }
+    
+    fn plan_migration(&self, migration: MigrationFile) -> Result<PlannedMigration> {
+        let statements = if migration.is_baseline {
+            Vec::new()
+        } else if migration.code.is_some() {
+            vec![format!("-- Rust migration: {}", migration.name)]
+        } else {
+            self.prepare_statements(&migration.up_sql, &migration.up_source, &migration.directives)?
+                .into_iter()
+                .map(|statement| statement.sql)
+                .collect()
+        };
+        
+        Ok(PlannedMigration {
+            version: migration.version,
+            name: migration.name,
+            checksum: migration.checksum,
+            is_baseline: migration.is_baseline,
+            statements,
+            directives: migration.directives,
+        })
+    }
+    
+    /// 静态检查所有迁移文件（包括已应用的迁移），不访问数据库
+    pub async fn lint(&self) -> Result<LintReport> {
+        let scanned = self.scan_all_migrations().await
+            .context("Failed to scan migration files")?;
+        lint::lint_migrations(scanned.versioned.values().chain(&scanned.repeatable), &self.config.lint)
+    }
+    
+    /// 导出数据库结构快照：当前数据库中的表、字典和视图，不含迁移历史表（及其升级备份表）和锁表
+    pub async fn dump_schema(&self) -> Result<SchemaDump> {
+        schema::dump(&self.connection_manager.get_client(), self.connection_manager.database(), &self.internal_table_names()).await
+    }
+    
+    /// 比较数据库的实际结构和结构快照（`schema_file`），报告所有差异
+    pub async fn check_drift(&self) -> Result<DriftReport> {
+        let path = self.config.schema_file.as_deref()
+            .ok_or_else(|| anyhow!("No schema snapshot configured (set schema_file)"))?;
+        let snapshot = tokio::fs::read_to_string(path).await
+            .with_context(|| format!("Failed to read schema snapshot {}", path))?;
+        
+        let expected = drift::parse_snapshot(&snapshot)
+            .with_context(|| format!("Invalid schema snapshot {}", path))?;
+        let actual = drift::live_schema(&self.connection_manager.get_client(), &self.internal_table_names()).await?;
+        
+        Ok(DriftReport {
+            snapshot: path.to_string(),
+            drift: drift::compare(&expected, &actual),
+        })
+    }
+    
+    /// 迁移前的漂移检测：待执行迁移会修改的表不参与比较；`allow_drift` 时只记录警告
+    ///
+    /// `affected_tables` 为 None（无法确定待执行迁移修改哪些表）或快照文件不存在时跳过。
+    async fn check_drift_before_migrate(&self, affected_tables: Option<BTreeSet<String>>) -> Result<()> {
+        let Some(path) = self.config.schema_file.as_deref() else {
+            return Ok(());
+        };
+        if !std::path::Path::new(path).exists() {
+            info!("Schema snapshot {} does not exist yet, skipping drift check", path);
+            return Ok(());
+        }
+        let Some(affected_tables) = affected_tables else {
+            warn!("Cannot determine which tables the pending migrations change, skipping drift check");
+            return Ok(());
+        };
+        
+        let mut report = self.check_drift().await
+            .context("Failed to check schema drift")?;
+        report.drift.retain(|drift| !affected_tables.contains(drift.table()));
+        
+        if !report.has_drift() {
+            debug!("No schema drift against {}", path);
+            return Ok(());
+        }
+        if self.config.allow_drift {
+            warn!("Schema drift allowed, continuing: {}", report);
+            return Ok(());
+        }
+        Err(DriftError { report }.into())
+    }
+    
+    /// 读取版本化迁移（迁移文件和注册的代码迁移）
     async fn scan_migration_files(&self) -> Result<BTreeMap<String, MigrationFile>> {
+        Ok(self.scan_all_migrations().await?.versioned)
+    }
+    
+    /// 读取并解析迁移文件（目录或嵌入集合），替换占位符，分出可重复迁移，并加入注册的代码迁移
+    async fn scan_all_migrations(&self) -> Result<ScannedMigrations> {
+        let mut migration_files = self.load_migration_source().await?;
+        for migration in migration_files.values_mut() {
+            self.expand_placeholders(migration)?;
+        }
+        
+        let (repeatable, mut versioned): (BTreeMap<_, _>, BTreeMap<_, _>) = migration_files
+            .into_iter()
+            .partition(|(_, migration)| migration.repeatable);
+        self.add_code_migrations(&mut versioned)?;
+        
+        Ok(ScannedMigrations {
+            versioned,
+            repeatable: repeatable.into_values().collect(),
+        })
+    }
+    
+    async fn load_migration_source(&self) -> Result<BTreeMap<String, MigrationFile>> {
+        match &self.source {
+            MigrationSource::Directory(path) => self.scan_migration_directory(path).await,
+            MigrationSource::Embedded(migrations) => {
+                let mut migration_files = BTreeMap::new();
+                // 已在编译期用相同的解析检查过，解析失败说明嵌入方式有误，直接报错
+                for (file_name, content) in migrations.files() {
+                    let migration = self.parse_migration_content(std::path::Path::new(file_name), content)
+                        .with_context(|| format!("Failed to parse embedded migration {}", file_name))?;
+                    debug!("Parsed embedded migration: {} - {}", migration.version, migration.name);
+                    migration_files.insert(migration.version.clone(), migration);
+                }
+                info!("Loaded {} embedded migrations", migration_files.len());
+                Ok(migration_files)
+            }
+        }
+    }
+    
+    /// 扫描迁移文件目录（`concurrent_file_scan` 为 true 时并发读取）
+    ///
+    /// 文件名不符合迁移命名的 `.sql` 文件会被跳过；迁移文件无法读取或解析（未知指令、
+    /// 未闭合的字符串等）时返回错误，避免缺少一个版本却继续执行后面的迁移。
+    async fn scan_migration_directory(&self, migrations_path: &str) -> Result<BTreeMap<String, MigrationFile>> {
         use tokio::fs;
         use std::path::Path;
         
-        let migrations_dir = Path::new(&self.migrations_path);
+        let migrations_dir = Path::new(migrations_path);
         if !migrations_dir.exists() {
-            warn!("Migration directory does not exist: {}", self.migrations_path);
+            warn!("Migration directory does not exist: {}", migrations_path);
             return Ok(BTreeMap::new());
         }
         
         let mut entries = fs::read_dir(migrations_dir).await
-            .with_context(|| format!("Failed to read migrations directory: {}", self.migrations_path))?;
-        
-        let mut join_set = JoinSet::new();
+            .with_context(|| format!("Failed to read migrations directory: {}", migrations_path))?;
         
-        // 并发读取所有SQL文件
+        let mut paths = Vec::new();
         while let Some(entry) = entries.next_entry().await? {
             let path = entry.path();
+            if path.extension() != Some(std::ffi::OsStr::new("sql")) {
+                continue;
+            }
             
-            if path.extension() == Some(std::ffi::OsStr::new("sql")) {
-                let path_clone = path.clone();
+            let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            match parser::parse_file_name(&file_name) {
+                Ok(_) => paths.push(path),
+                Err(e) => warn!("Skipping {:?}: {}", path, e),
+            }
+        }
+        paths.sort();
+        
+        let mut contents = Vec::with_capacity(paths.len());
+        if self.config.concurrent_file_scan {
+            // 并发读取所有SQL文件
+            let mut join_set = JoinSet::new();
+            for path in paths {
                 join_set.spawn(async move {
-                    let content = tokio::fs::read_to_string(&path_clone).await?;
-                    Ok::<_, anyhow::Error>((path_clone, content))
+                    let content = tokio::fs::read_to_string(&path).await;
+                    (path, content)
                 });
             }
+            while let Some(result) = join_set.join_next().await {
+                contents.push(result?);
+            }
+            contents.sort_by(|(a, _), (b, _)| a.cmp(b));
+        } else {
+            for path in paths {
+                let content = fs::read_to_string(&path).await;
+                contents.push((path, content));
+            }
         }
         
         let mut migration_files = BTreeMap::new();
         
-        // 收集所有文件内容并解析
-        while let Some(result) = join_set.join_next().await {
-            match result? {
-                Ok((path, content)) => {
-                    match self.parse_migration_content(&path, &content) {
-                        Ok(migration) => {
-                            debug!("Parsed migration: {} - {}", migration.version, migration.name);
-                            migration_files.insert(migration.version.clone(), migration);
-                        }
-                        Err(e) => {
-                            warn!("Failed to parse migration file {:?}: {}", path, e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!("Failed to read migration file: {}", e);
-                }
+        // 解析所有文件内容
+        for (path, content) in contents {
+            let content = content
+                .with_context(|| format!("Failed to read migration file {:?}", path))?;
+            let migration = self.parse_migration_content(&path, &content)
+                .with_context(|| format!("Failed to parse migration file {:?}", path))?;
+            debug!("Parsed migration: {} - {}", migration.version, migration.name);
+            
+            // V1 和 V001 版本字符串不同，但版本号相同
+            let number = |m: &MigrationFile| m.version().ok().filter(|_| !m.repeatable).map(|v| v.number);
+            let duplicate = migration_files.values().find(|existing: &&MigrationFile| {
+                existing.version == migration.version
+                    || number(existing).is_some_and(|n| Some(n) == number(&migration))
+            });
+            if let Some(existing) = duplicate {
+                return Err(anyhow!(
+                    "Duplicate migration version {}: {} and {}",
+                    migration.version, existing.file_name, migration.file_name
+                ));
             }
+            migration_files.insert(migration.version.clone(), migration);
         }
         
         info!("Scanned {} migration files", migration_files.len());
@@ -352,127 +988,154 @@ impl SimpleMigrator {
     
     /// 解析单个迁移文件内容
     fn parse_migration_content(&self, file_path: &std::path::Path, content: &str) -> Result<MigrationFile> {
-        use regex::Regex;
-        
-        // 解析文件名：V001__create_users_table.sql
-        let filename = file_path.file_stem()
+        let file_name = file_path.file_name()
             .and_then(|s| s.to_str())
-            .ok_or_else(|| anyhow!("Invalid filename: {:?}", file_path))?;
-        
-        let version_regex = Regex::new(r"^V(\d+)__(.+)$")
-            .context("Failed to compile version regex")?;
-        
-        let captures = version_regex.captures(filename)
-            .ok_or_else(|| anyhow!("Invalid migration filename format: {} (expected: V001__description.sql)", filename))?;
-        
-        let version = captures.get(1).unwrap().as_str().to_string();
-        let name = captures.get(2).unwrap().as_str().replace('_', " ");
-        
-        // 解析SQL内容
-        let (up_sql, down_sql) = self.parse_sql_content(content)?;
+            .ok_or_else(|| anyhow!("Invalid filename: {:?}", file_path))?
+            .to_string();
+        
+        // 解析文件名（V001__create_users_table.sql）和SQL内容
+        let (parsed_name, sections) = parser::parse_migration(&file_name, content)?;
+        let repeatable = parsed_name.is_repeatable();
+        let up_sql = sections.up_sql;
+        let down_sql = sections.down_sql;
+        let directives = sections.directives;
+        
+        if repeatable && down_sql.is_some() {
+            warn!("Repeatable migration {} has a Down section, it will be ignored", file_name);
+        }
         
         // 检查是否为基线迁移
-        let is_baseline = version == "000" || up_sql.trim().is_empty();
+        let is_baseline = !repeatable && (parsed_name.is_baseline() || up_sql.trim().is_empty());
         
         // 计算校验和
         let checksum = self.calculate_checksum(&up_sql);
         
         Ok(MigrationFile {
-            version,
-            name,
+            version: parsed_name.version,
+            name: parsed_name.name,
+            up_source: SourceMap::new(&file_name, sections.up_lines),
+            down_source: SourceMap::new(&file_name, sections.down_lines),
+            file_name,
             up_sql,
             down_sql,
             checksum,
             is_baseline,
+            code: None,
+            repeatable,
+            directives,
         })
     }
     
-    /// 解析SQL内容（分离UP和DOWN部分）
-    fn parse_sql_content(&self, content: &str) -> Result<(String, Option<String>)> {
-        let lines: Vec<&str> = content.lines().collect();
-        let mut up_sql = String::new();
-        let mut down_sql: Option<String> = None;
-        let mut current_section = Section::Up;
-        
-        for line in lines {
-            let trimmed = line.trim();
-            
-            if trimmed == "-- +migrate Up" {
-                current_section = Section::Up;
-                continue;
-            } else if trimmed == "-- +migrate Down" {
-                current_section = Section::Down;
-                down_sql = Some(String::new());
-                continue;
-            }
+    /// 替换 Up/Down 中的 `${name}` 占位符；校验和已在解析时基于替换前的文本计算
+    fn expand_placeholders(&self, migration: &mut MigrationFile) -> Result<()> {
+        migration.up_sql = self.placeholders.expand(&migration.up_sql)
+            .with_context(|| format!("Failed to expand placeholders in {}", migration.file_name))?;
+        if let Some(down_sql) = &migration.down_sql {
+            migration.down_sql = Some(self.placeholders.expand(down_sql)
+                .with_context(|| format!("Failed to expand placeholders in Down section of {}", migration.file_name))?);
+        }
+        Ok(())
+    }
+    
+    /// 将注册的代码迁移加入迁移列表，版本号固定补零到 3 位
+    ///
+    /// 历史表中的版本号必须稳定：如果按迁移文件的位数补零，之后加入更长的文件名（如 `V1000__x.sql`）
+    /// 会让已应用的代码迁移换一个版本字符串，被当作待执行迁移再次运行。
+    fn add_code_migrations(&self, migration_files: &mut BTreeMap<String, MigrationFile>) -> Result<()> {
+        for code in &self.code_migrations {
+            let migration = code.migration();
+            let number = migration.version();
             
-            // 跳过元数据注释
-            if trimmed.starts_with("-- ") && !trimmed.contains("/*") && !trimmed.contains("--") {
-                continue;
+            if let Some(existing) = migration_files.values()
+                .find(|m| m.version().map(|v| v.number == number).unwrap_or(false)) {
+                return Err(anyhow!(
+                    "Duplicate migration version {}: Rust migration '{}' conflicts with {}",
+                    number, migration.name(), existing.file_name
+                ));
             }
             
-            match current_section {
-                Section::Up => {
-                    up_sql.push_str(line);
-                    up_sql.push('\n');
-                }
-                Section::Down => {
-                    if let Some(ref mut down) = down_sql {
-                        down.push_str(line);
-                        down.push('\n');
-                    }
-                }
-            }
+            let version = format!("{:0width$}", number, width = scaffold::MIN_VERSION_WIDTH);
+            debug!("Registered Rust migration: {} - {}", version, migration.name());
+            migration_files.insert(version.clone(), MigrationFile {
+                version,
+                name: migration.name().to_string(),
+                file_name: format!("rust:{}", migration.name()),
+                up_sql: String::new(),
+                down_sql: None,
+                checksum: self.calculate_checksum(migration.checksum_version()),
+                is_baseline: false,
+                up_source: SourceMap::default(),
+                down_source: SourceMap::default(),
+                code: Some(code.clone()),
+                repeatable: false,
+                directives: MigrationDirectives::default(),
+            });
         }
         
-        Ok((up_sql.trim().to_string(), down_sql.map(|s| s.trim().to_string())))
+        Ok(())
     }
     
     /// 验证已应用迁移的校验和
     async fn validate_applied_migrations(&self, migration_files: &BTreeMap<String, MigrationFile>) -> Result<()> {
+        if !self.config.validate_checksums {
+            warn!("Checksum validation is disabled, skipping validation of applied migrations");
+            return Ok(());
+        }
+        
+        let (mismatches, missing_files) = self.check_applied_checksums(migration_files).await?;
+        
+        for version in &missing_files {
+            warn!("Applied migration {} not found in migration files", version);
+        }
+        
+        if !mismatches.is_empty() {
+            return Err(ValidationError { mismatches }.into());
+        }
+        
+        info!("Migration validation passed");
+        Ok(())
+    }
+    
+    /// 对比已应用迁移与迁移文件的校验和，返回校验和不一致的迁移和缺失文件的版本
+    async fn check_applied_checksums(
+        &self, 
+        migration_files: &BTreeMap<String, MigrationFile>
+    ) -> Result<(Vec<ChecksumMismatch>, Vec<String>)> {
         let table_name = self.get_migration_table_name();
         
         if !self.table_exists(&table_name).await? {
             debug!("Migration table does not exist, skipping validation");
-            return Ok(());
+            return Ok((Vec::new(), Vec::new()));
         }
         
-        let query = format!("SELECT version, checksum FROM {} WHERE success = 1", table_name);
+        let query = format!(
+            "SELECT version, argMax(checksum, finished_at) FROM {} FINAL WHERE success = 1 AND {} GROUP BY version", 
+            table_name, VERSIONED_ONLY
+        );
         
-        let applied_records = match self.query_version_checksum_pairs(&query).await {
-            Ok(records) => records,
-            Err(e) => {
-                warn!("Failed to query applied migrations for validation: {}", e);
-                return Ok(()); // 如果查询失败，跳过验证，允许迁移继续
-            }
-        };
+        let applied_records = self.query_version_checksum_pairs(&query).await
+            .context("Failed to query applied migrations for validation")?;
         
         debug!("Validating {} applied migrations", applied_records.len());
         
-        let mut validation_errors = Vec::new();
+        let mut mismatches = Vec::new();
+        let mut missing_files = Vec::new();
         
         for (version, stored_checksum) in applied_records {
             if let Some(migration_file) = migration_files.get(&version) {
                 if migration_file.checksum != stored_checksum {
-                    validation_errors.push(format!(
-                        "Migration {}: checksum mismatch (stored: {}, file: {})",
-                        version, stored_checksum, migration_file.checksum
-                    ));
+                    mismatches.push(ChecksumMismatch {
+                        version,
+                        stored_checksum,
+                        file_checksum: migration_file.checksum.clone(),
+                    });
                 }
             } else {
-                warn!("Applied migration {} not found in migration files", version);
+                missing_files.push(version);
             }
         }
         
-        if !validation_errors.is_empty() {
-            return Err(anyhow!(
-                "Migration validation failed:\n{}",
-                validation_errors.join("\n")
-            ));
-        }
-        
-        info!("Migration validation passed");
-        Ok(())
+        Ok((mismatches, missing_files))
     }
     
     /// 获取已应用的迁移版本
@@ -494,7 +1157,10 @@ impl SimpleMigrator {
             }
         }
         
-        let query = format!("SELECT version FROM {} WHERE success = 1 ORDER BY version", table_name);
+        let query = format!(
+            "SELECT DISTINCT version FROM {} FINAL WHERE success = 1 AND {} ORDER BY version", 
+            table_name, VERSIONED_ONLY
+        );
         
         match self.query_all_strings(&query).await {
             Ok(versions) => {
@@ -506,13 +1172,122 @@ impl SimpleMigrator {
                       });
                 Ok(versions.into_iter().collect())
             }
-            Err(e) => {
-                error!("Failed to query applied versions from table '{}': {}", table_name, e);
-                // 这里不应该静默返回空集合，而应该传播错误
-                // 除非我们确定这是一个可以恢复的错误
-                Err(e.context("Failed to retrieve applied migration versions"))
+            Err(e) => {
+                error!("Failed to query applied versions from table '{}': {}", table_name, e);
+                // 这里不应该静默返回空集合，而应该传播错误
+                // 除非我们确定这是一个可以恢复的错误
+                Err(e.context("Failed to retrieve applied migration versions"))
+            }
+        }
+    }
+    
+    /// 每个可重复迁移最近一次成功执行时的校验和
+    async fn get_repeatable_checksums(&self) -> Result<HashMap<String, String>> {
+        let table_name = self.get_migration_table_name();
+        if !self.table_exists(&table_name).await? {
+            return Ok(HashMap::new());
+        }
+        
+        let query = format!(
+            "SELECT version, argMax(checksum, finished_at) FROM {} FINAL WHERE success = 1 AND NOT {} GROUP BY version",
+            table_name, VERSIONED_ONLY
+        );
+        Ok(self.query_version_checksum_pairs(&query).await?.into_iter().collect())
+    }
+    
+    /// 从未执行或校验和与最近一次成功执行不同的可重复迁移
+    async fn get_pending_repeatables(&self, repeatable: Vec<MigrationFile>) -> Result<Vec<MigrationFile>> {
+        if repeatable.is_empty() {
+            return Ok(repeatable);
+        }
+        
+        let applied = self.get_repeatable_checksums().await?;
+        Ok(repeatable.into_iter()
+            .filter(|m| applied.get(&m.version) != Some(&m.checksum))
+            .collect())
+    }
+    
+    async fn get_repeatable_status(&self, repeatable: Vec<MigrationFile>) -> Result<Vec<RepeatableMigrationStatus>> {
+        if repeatable.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let applied = self.get_repeatable_checksums().await?;
+        Ok(repeatable.into_iter()
+            .map(|m| {
+                let state = match applied.get(&m.version) {
+                    None => RepeatableState::Pending,
+                    Some(checksum) if *checksum != m.checksum => RepeatableState::Outdated,
+                    Some(_) => RepeatableState::UpToDate,
+                };
+                RepeatableMigrationStatus { name: m.name, file_name: m.file_name, state }
+            })
+            .collect())
+    }
+    
+    /// 最近一次尝试失败、但已完成部分语句的版本化迁移
+    async fn get_partial_migrations(&self) -> Result<Vec<PartialMigrationRow>> {
+        let table_name = self.get_migration_table_name();
+        if !self.table_exists(&table_name).await? {
+            return Ok(Vec::new());
+        }
+        
+        let query = format!(
+            "SELECT version, argMax(checksum, finished_at) AS checksum, \
+                    argMax(statements_completed, finished_at) AS completed, \
+                    argMax(statements_total, finished_at) AS total \
+             FROM {} FINAL WHERE {} GROUP BY version \
+             HAVING argMax(success, finished_at) = 0 AND completed > 0",
+            table_name, VERSIONED_ONLY
+        );
+        
+        self.connection_manager.get_client()
+            .query(&query)
+            .fetch_all::<PartialMigrationRow>()
+            .await
+            .context("Failed to query partially applied migrations")
+    }
+    
+    /// 每个部分执行的待执行迁移应跳过的语句数
+    ///
+    /// 非 resume 模式下只输出警告，迁移从第一条语句重新执行。
+    /// resume 模式下迁移文件在失败后被修改时返回错误。
+    async fn get_resume_points(&self, pending: &[MigrationFile], resume: bool) -> Result<HashMap<String, u32>> {
+        let mut resume_points = HashMap::new();
+        
+        for partial in self.get_partial_migrations().await? {
+            let Some(migration) = pending.iter().find(|m| m.version == partial.version) else {
+                continue;
+            };
+            
+            if !resume {
+                warn!(
+                    "Migration {} previously failed after {}/{} statements; it will run from the first statement \
+                     (use resume to continue from statement {})",
+                    partial.version, partial.completed, partial.total, partial.completed + 1
+                );
+                continue;
+            }
+            
+            if migration.checksum != partial.checksum {
+                return Err(anyhow!(
+                    "Cannot resume migration {}: the file changed since the failed attempt \
+                     (stored checksum: {}, file checksum: {})",
+                    partial.version, partial.checksum, migration.checksum
+                ));
+            }
+            if migration.code.is_some() {
+                continue;
             }
+            
+            info!(
+                "Resuming migration {} from statement {}/{}",
+                partial.version, partial.completed + 1, partial.total
+            );
+            resume_points.insert(partial.version, partial.completed);
         }
+        
+        Ok(resume_points)
     }
     
     /// 确定待执行的迁移
@@ -580,7 +1355,12 @@ impl SimpleMigrator {
     }
     
     /// 执行待处理的迁移
-    async fn execute_pending_migrations(&self, pending: Vec<MigrationFile>) -> Result<MigrationSummary> {
+    async fn execute_pending_migrations(
+        &self, 
+        pending: Vec<MigrationFile>, 
+        resume_points: &HashMap<String, u32>,
+        run: &RunContext
+    ) -> Result<MigrationSummary> {
         let mut summary = MigrationSummary::new();
         let total = pending.len();
         
@@ -592,9 +1372,12 @@ impl SimpleMigrator {
             
             info!("Executing migration: {}", migration.name);
             
-            match self.execute_migration(migration).await {
-                Ok(record) => {
-                    summary.successful.push(record);
+            let resume_from = resume_points.get(&migration.version).copied().unwrap_or(0);
+            match self.execute_migration(migration, resume_from, run).await {
+                Ok(executed) => {
+                    summary.successful.push(executed.record);
+                    summary.ddl_results.extend(executed.ddl_results);
+                    summary.statement_stats.extend(executed.statement_stats);
                     info!("Migration completed successfully");
                 }
                 Err(e) => {
@@ -618,9 +1401,42 @@ impl SimpleMigrator {
         Ok(summary)
     }
     
-    /// 执行单个迁移
-    async fn execute_migration(&self, migration: &MigrationFile) -> Result<MigrationRecord> {
+    /// 执行单个迁移，跳过前 `resume_from` 条已在之前的尝试中完成的语句
+    async fn execute_migration(
+        &self, 
+        migration: &MigrationFile, 
+        resume_from: u32,
+        run: &RunContext
+    ) -> Result<ExecutedMigration> {
         let start_time = Instant::now();
+        let started_at = Utc::now();
+        let attempt = self.next_attempt_number(&migration.version).await?;
+        let mut progress = StatementProgress { completed: resume_from, ..Default::default() };
+        
+        // 本次尝试的记录；执行过程中作为检查点写入，结束后更新结果
+        let mut record = MigrationRecord {
+            run_id: run.run_id.clone(),
+            version: migration.version.clone(),
+            attempt,
+            name: migration.name.clone(),
+            checksum: migration.checksum.clone(),
+            success: false,
+            error_message: String::new(),
+            execution_time_ms: 0,
+            started_at,
+            finished_at: started_at,
+            hostname: run.hostname.clone(),
+            os_user: run.os_user.clone(),
+            tool_version: super::VERSION.to_string(),
+            statements_total: 0,
+            statements_completed: resume_from,
+            note: CHECKPOINT_NOTE.to_string(),
+            query_ids: Vec::new(),
+            read_rows: 0,
+            read_bytes: 0,
+            memory_usage: 0,
+            query_duration_ms: 0,
+        };
         
         info!("Starting migration: {} - {}", migration.version, migration.name);
         debug!("Migration checksum: {}", migration.checksum);
@@ -628,20 +1444,49 @@ impl SimpleMigrator {
         // 对于基线迁移，跳过SQL执行
         let execution_result = if migration.is_baseline {
             info!("Baseline migration detected, skipping SQL execution");
-            Ok(())
+            Ok(Vec::new())
+        } else if let Some(code) = &migration.code {
+            info!("Executing Rust migration");
+            progress.total = 1;
+            let client = self.connection_manager.get_client();
+            match code.migration().up(&client).await {
+                Ok(()) => {
+                    progress.completed = 1;
+                    Ok(Vec::new())
+                }
+                Err(e) => Err(e.context(format!(
+                    "Failed to execute migration {}: {}", migration.version, migration.name
+                ))),
+            }
         } else {
             info!("Executing migration SQL with {} characters", migration.up_sql.len());
             let preview_length = std::cmp::min(200, migration.up_sql.chars().count());
             let sql_preview: String = migration.up_sql.chars().take(preview_length).collect();
             debug!("Migration SQL preview: {}", sql_preview);
             
-            self.execute_sql_statements(&migration.up_sql).await
+            if !migration.directives.is_empty() {
+                info!("Migration directives: {}", migration.directives);
+            }
+            
+            self.execute_sql_statements(
+                &migration.up_sql, 
+                &migration.up_source, 
+                &migration.directives, 
+                &mut progress, 
+                Some(&record)
+            ).await
                 .with_context(|| format!("Failed to execute migration {}: {}", migration.version, migration.name))
         };
         
         let execution_time = start_time.elapsed();
         let success = execution_result.is_ok();
-        let error_message = execution_result.err().map(|e| e.to_string()).unwrap_or_default();
+        let (mut ddl_results, error_message) = match execution_result {
+            Ok(results) => (results, String::new()),
+            Err(e) => (Vec::new(), e.to_string()),
+        };
+        for result in &mut ddl_results {
+            result.version = migration.version.clone();
+        }
         
         if success {
             info!("Migration {} completed successfully in {:?}", migration.version, execution_time);
@@ -650,45 +1495,87 @@ impl SimpleMigrator {
         }
         
         // 记录迁移结果
-        let record = MigrationRecord {
-            version: migration.version.clone(),
-            name: migration.name.clone(),
-            applied_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
-            execution_time_ms: execution_time.as_millis() as u64,
-            checksum: migration.checksum.clone(),
-            success,
-            error_message: error_message.clone(),
+        record.success = success;
+        record.error_message = error_message.clone();
+        record.execution_time_ms = execution_time.as_millis() as u64;
+        record.finished_at = Utc::now();
+        record.statements_total = progress.total;
+        record.statements_completed = progress.completed;
+        record.note = if resume_from > 0 {
+            format!("resumed from statement {}", resume_from + 1)
+        } else {
+            String::new()
         };
         
-        // 保存到数据库（无论成功失败都记录）
-        match self.save_migration_record(&record).await {
-            Ok(_) => debug!("Migration record saved to database"),
-            Err(e) => warn!("Failed to save migration record: {}", e),
+        // 执行统计只用于报告，查询失败不影响迁移结果
+        let statement_stats = match query_log::fetch_statement_stats(
+            &self.connection_manager.get_client(), 
+            &migration.version, 
+            &progress.query_ids
+        ).await {
+            Ok(stats) => stats,
+            Err(e) => {
+                warn!("Failed to collect query_log statistics for migration {}: {:#}", migration.version, e);
+                Vec::new()
+            }
+        };
+        record.query_ids = progress.query_ids.iter().map(|(_, id)| id.clone()).collect();
+        record.read_rows = statement_stats.iter().map(|s| s.read_rows).sum();
+        record.read_bytes = statement_stats.iter().map(|s| s.read_bytes).sum();
+        record.memory_usage = statement_stats.iter().map(|s| s.memory_usage).max().unwrap_or(0);
+        record.query_duration_ms = statement_stats.iter().map(|s| s.duration_ms).sum();
+        
+        // 保存到数据库（无论成功失败都记录）；记录丢失会导致迁移被重复执行，因此视为错误
+        if let Err(e) = self.save_migration_record(&record).await {
+            error!("Failed to save migration record for {}: {:#}", migration.version, e);
+            return Err(if success {
+                anyhow!("Migration {} was applied but its history record could not be saved: {:#}", 
+                        migration.version, e)
+            } else {
+                anyhow!("Migration execution failed: {}\nAdditionally, its history record could not be saved: {:#}", 
+                        error_message, e)
+            });
         }
+        debug!("Migration record saved to database");
         
         // 如果执行失败，返回错误
         if !success {
             return Err(anyhow!("Migration execution failed: {}", error_message));
         }
         
-        Ok(record)
+        Ok(ExecutedMigration { record, ddl_results, statement_stats })
     }
     
-    /// 执行SQL语句（支持多语句）
-    async fn execute_sql_statements(&self, sql: &str) -> Result<()> {
+    /// 执行SQL语句（支持多语句），返回其中 ON CLUSTER 语句的各节点结果
+    ///
+    /// 跳过前 `progress.completed` 条语句。提供 `checkpoint` 时，每条语句完成后将该记录
+    /// （带当前进度）写入历史表，进程中断后也能从下一条语句继续；每条语句使用由该记录
+    /// 的版本和 run_id 生成的 query_id，并记录在 `progress.query_ids` 中。
+    async fn execute_sql_statements(
+        &self, 
+        sql: &str, 
+        source: &SourceMap, 
+        directives: &MigrationDirectives,
+        progress: &mut StatementProgress,
+        checkpoint: Option<&MigrationRecord>
+    ) -> Result<Vec<DdlStatementResult>> {
         if sql.trim().is_empty() {
             debug!("Empty SQL content, skipping execution");
-            return Ok(());
+            return Ok(Vec::new());
         }
         
-        let statements = self.split_sql_statements(sql);
+        let statements = self.prepare_statements(sql, source, directives)?;
+        let mut ddl_results = Vec::new();
+        progress.total = statements.len() as u32;
         info!("Executing {} SQL statements", statements.len());
         
-        for (i, statement) in statements.iter().enumerate() {
-            let trimmed = statement.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
+        if progress.completed > 0 {
+            info!("Skipping {} statements completed by a previous attempt", progress.completed);
+        }
+        
+        for (i, statement) in statements.iter().enumerate().skip(progress.completed as usize) {
+            let trimmed = statement.sql.as_str();
+            let location = source.locate(statement.line, statement.column);
             
             let statement_preview = if trimmed.chars().count() > 100 {
                 let truncated: String = trimmed.chars().take(100).collect();
@@ -697,17 +1584,75 @@ impl SimpleMigrator {
                 trimmed.to_string()
             };
             
-            info!("Executing statement {}/{}: {}", 
-                   i + 1, statements.len(), statement_preview);
+            info!("Executing statement {}/{} ({}): {}", 
+                   i + 1, statements.len(), location, statement_preview);
+            
+            if self.cluster.is_some() && cluster::is_ddl(trimmed) && !cluster::is_on_cluster(trimmed) {
+                warn!("Statement {}/{} ({}) has no ON CLUSTER clause and will only run on one node", 
+                      i + 1, statements.len(), location);
+            }
+            
+            // 会产生 mutation 的语句：先记录目标表上已有的 mutation，执行后等待新创建的 mutation 完成
+            let mutation_target = if self.config.wait_for_mutations {
+                mutations::mutation_target(trimmed)
+            } else {
+                None
+            };
+            let mutation_cluster = self.cluster.as_ref().map(|cluster| cluster.name.as_str());
+            let existing_mutations = match &mutation_target {
+                Some(target) => Some(mutations::existing_mutations(
+                    &self.connection_manager.get_client(),
+                    target,
+                    mutation_cluster,
+                ).await?),
+                None => None,
+            };
+            
+            let mut query_id = checkpoint.map(|record| {
+                query_log::query_id(&self.service_name, &record.version, i + 1, &record.run_id)
+            });
+            if let Some(query_id) = &query_id {
+                debug!("Statement {}/{} query_id: {}", i + 1, statements.len(), query_id);
+            }
+            
+            let result = self.execute_statement_with_retry(trimmed, directives, &mut query_id).await;
+            // 重试后 query_id 为最后一次尝试的 query_id
+            if let Some(query_id) = query_id {
+                progress.query_ids.push((i + 1, query_id));
+            }
+            let mut result = result.map(|hosts| {
+                if let Some(hosts) = hosts {
+                    ddl_results.push(DdlStatementResult {
+                        version: String::new(),
+                        statement_index: i + 1,
+                        statement: statement_preview.clone(),
+                        hosts,
+                    });
+                }
+            });
+            
+            if let (Ok(()), Some(target), Some(existing)) = (&result, &mutation_target, &existing_mutations) {
+                result = mutations::wait_for_mutations(
+                    &self.connection_manager.get_client(),
+                    target,
+                    mutation_cluster,
+                    existing,
+                    self.config.mutation_timeout,
+                ).await;
+            }
             
-            match self.execute_ddl(trimmed).await {
+            match result {
                 Ok(_) => {
+                    progress.completed = i as u32 + 1;
                     info!("Statement {}/{} executed successfully", i + 1, statements.len());
+                    if let Some(record) = checkpoint {
+                        self.save_checkpoint(record, progress).await;
+                    }
                 }
                 Err(e) => {
                     let error_context = format!(
-                        "Failed to execute statement {}/{}: {}\nSQL: {}\nFull error: {}", 
-                        i + 1, statements.len(), statement_preview, trimmed, e
+                        "Failed to execute statement {}/{} at {}\nSQL: {}\nFull error: {}", 
+                        i + 1, statements.len(), location, trimmed, e
                     );
                     error!("{}", error_context);
                     return Err(anyhow!(error_context));
@@ -716,90 +1661,172 @@ impl SimpleMigrator {
         }
         
         info!("All {} SQL statements executed successfully", statements.len());
-        Ok(())
+        Ok(ddl_results)
     }
     
-    /// 改进的SQL语句分割
-    fn split_sql_statements(&self, sql: &str) -> Vec<String> {
-        let mut statements = Vec::new();
-        let mut current_statement = String::new();
-        let mut in_single_quote = false;
-        let mut in_double_quote = false;
-        let mut in_comment = false;
-        let mut prev_char = '\0';
-        
-        for ch in sql.chars() {
-            match ch {
-                '\'' if !in_double_quote && !in_comment => {
-                    // 处理转义的单引号
-                    if prev_char != '\\' {
-                        in_single_quote = !in_single_quote;
-                    }
-                    current_statement.push(ch);
-                }
-                '"' if !in_single_quote && !in_comment => {
-                    // 处理转义的双引号
-                    if prev_char != '\\' {
-                        in_double_quote = !in_double_quote;
-                    }
-                    current_statement.push(ch);
-                }
-                '-' if prev_char == '-' && !in_single_quote && !in_double_quote => {
-                    in_comment = true;
-                    current_statement.push(ch);
-                }
-                '\n' if in_comment => {
-                    in_comment = false;
-                    current_statement.push(ch);
-                }
-                ';' if !in_single_quote && !in_double_quote && !in_comment => {
-                    let statement = current_statement.trim();
-                    if !statement.is_empty() {
-                        statements.push(statement.to_string());
+    /// 执行迁移中的一条语句，按指令附加查询设置、超时和重试；ON CLUSTER 语句返回各节点结果
+    ///
+    /// 只重试网络错误和超时（见 `server_error::is_transient`），语法错误等服务器异常直接返回。
+    /// 每次重试使用新的 query_id（`query_id` 更新为最后一次尝试的 query_id），
+    /// 客户端超时后先终止服务器上可能仍在运行的查询再重试。
+    async fn execute_statement_with_retry(
+        &self, 
+        sql: &str, 
+        directives: &MigrationDirectives,
+        query_id: &mut Option<String>
+    ) -> Result<Option<Vec<cluster::DdlHostResult>>> {
+        let base_query_id = query_id.clone();
+        let mut attempt = 1;
+        loop {
+            let execution = self.execute_statement(sql, directives, query_id.as_deref());
+            let result = match directives.timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, execution).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        self.kill_query(query_id.as_deref()).await;
+                        Err(anyhow::Error::new(clickhouse::error::Error::TimedOut)
+                            .context(format!("Statement timed out after {:?}", timeout)))
                     }
-                    current_statement.clear();
-                }
-                _ => {
-                    current_statement.push(ch);
+                },
+                None => execution.await,
+            };
+            
+            match result {
+                Err(e) if attempt <= directives.retries && server_error::is_transient(&e) => {
+                    let delay = std::time::Duration::from_secs(2u64.pow(attempt.min(5)));
+                    warn!("Statement failed, retrying ({}/{}) in {:?}: {}", attempt, directives.retries, delay, e);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    *query_id = base_query_id.as_deref().map(|id| query_log::attempt_query_id(id, attempt));
                 }
+                result => return result,
+            }
+        }
+    }
+    
+    /// 终止超时的查询（服务器上可能仍在运行）；失败时只记录警告
+    async fn kill_query(&self, query_id: Option<&str>) {
+        let Some(query_id) = query_id else {
+            return;
+        };
+        
+        let result = self.connection_manager.get_client()
+            .query("KILL QUERY WHERE query_id = ? ASYNC")
+            .bind(query_id)
+            .execute()
+            .await;
+        if let Err(e) = result {
+            warn!("Failed to kill timed out query {}: {}", query_id, e);
+        }
+    }
+    
+    async fn execute_statement(
+        &self, 
+        sql: &str, 
+        directives: &MigrationDirectives,
+        query_id: Option<&str>
+    ) -> Result<Option<Vec<cluster::DdlHostResult>>> {
+        if cluster::is_on_cluster(sql) {
+            return self.execute_on_cluster(sql, directives, query_id).await.map(Some);
+        }
+        
+        debug!("Executing statement: {}", sql);
+        if let Err(e) = self.statement_query(sql, directives, query_id).execute().await {
+            // 保留 clickhouse 错误作为 source，供重试判断错误类型
+            let message = format!("Failed to execute DDL: {}\nSQL: {}", e, sql);
+            return Err(anyhow::Error::new(e).context(message));
+        }
+        Ok(None)
+    }
+    
+    /// 创建迁移语句的查询，附加 query_id 和指令中的查询设置
+    fn statement_query(&self, sql: &str, directives: &MigrationDirectives, query_id: Option<&str>) -> clickhouse::query::Query {
+        let mut query = self.connection_manager.get_client().query(sql);
+        if let Some(query_id) = query_id {
+            query = query.with_option("query_id", query_id);
+        }
+        if let Some(timeout) = directives.timeout {
+            if !directives.settings.contains_key("max_execution_time") {
+                query = query.with_option("max_execution_time", timeout.as_secs().max(1).to_string());
             }
-            prev_char = ch;
         }
+        for (name, value) in &directives.settings {
+            query = query.with_option(name, value);
+        }
+        query
+    }
+    
+    /// 将迁移SQL分割为实际执行的语句列表（按词法分析分割后替换集群占位符）
+    ///
+    /// 带 `-- +nosplit` 指令时整段 SQL 作为一条语句。
+    fn prepare_statements(
+        &self, 
+        sql: &str, 
+        source: &SourceMap, 
+        directives: &MigrationDirectives
+    ) -> Result<Vec<SqlStatement>> {
+        let split = if directives.no_split {
+            lexer::single_statement(sql).map(|statement| statement.into_iter().collect())
+        } else {
+            lexer::split_statements(sql)
+        };
+        let mut statements = split
+            .map_err(|e| anyhow!("{}: {}", source.locate(e.line, e.column), e.message))?;
         
-        // 添加最后一个语句
-        let final_statement = current_statement.trim();
-        if !final_statement.is_empty() {
-            statements.push(final_statement.to_string());
+        if let Some(cluster) = &self.cluster {
+            for statement in &mut statements {
+                statement.sql = cluster.substitute_macro(&statement.sql);
+            }
         }
         
-        statements
+        Ok(statements)
+    }
+    
+    /// 下一次执行尝试的编号
+    async fn next_attempt_number(&self, version: &str) -> Result<u32> {
+        let query = format!("SELECT count() FROM {} FINAL WHERE version = ?", self.get_migration_table_name());
+        
+        let attempts = self.connection_manager.get_client()
+            .query(&query)
+            .bind(version)
+            .fetch_one::<u64>()
+            .await
+            .context("Failed to count previous migration attempts")?;
+        
+        Ok(attempts as u32 + 1)
     }
     
     /// 保存迁移记录
     async fn save_migration_record(&self, record: &MigrationRecord) -> Result<()> {
         let table_name = self.get_migration_table_name();
         
-        let insert_sql = format!(
-            r#"
-            INSERT INTO {table_name} 
-            (version, name, applied_at, execution_time_ms, checksum, success, error_message)
-            VALUES ('{}', '{}', '{}', {}, '{}', {}, '{}')
-            "#,
-            record.version,
-            record.name.replace('\'', "''"), // 转义单引号
-            record.applied_at,
-            record.execution_time_ms,
-            record.checksum,
-            record.success as u8,
-            record.error_message.replace('\'', "''")
-        );
+        let mut insert = self.connection_manager.get_client()
+            .insert::<MigrationRecord>(&table_name)
+            .context("Failed to prepare migration record insert")?;
         
-        self.execute_ddl(&insert_sql).await
+        insert.write(record).await
+            .context("Failed to write migration record")?;
+        insert.end().await
             .context("Failed to insert migration record")?;
         
         Ok(())
     }
     
+    /// 写入执行中的检查点记录；失败只记录警告，最终记录仍会保存进度
+    async fn save_checkpoint(&self, record: &MigrationRecord, progress: &StatementProgress) {
+        let mut checkpoint = record.clone();
+        checkpoint.finished_at = Utc::now();
+        checkpoint.execution_time_ms = (checkpoint.finished_at - checkpoint.started_at)
+            .num_milliseconds()
+            .max(0) as u64;
+        checkpoint.statements_total = progress.total;
+        checkpoint.statements_completed = progress.completed;
+        
+        if let Err(e) = self.save_migration_record(&checkpoint).await {
+            warn!("Failed to save checkpoint for migration {}: {:#}", record.version, e);
+        }
+    }
+    
     /// 计算校验和
     fn calculate_checksum(&self, content: &str) -> String {
         let mut hasher = Sha256::new();
@@ -809,8 +1836,183 @@ impl SimpleMigrator {
     
     /// 是否在失败时继续执行
     fn should_continue_on_failure(&self) -> bool {
-        std::env::var("CONTINUE_ON_MIGRATION_FAILURE")
-            .unwrap_or_default() == "true"
+        self.config.continue_on_failure
+    }
+    
+    /// 将版本号不大于 `version` 的所有迁移记录为已应用（使用文件当前的校验和），不执行任何 SQL
+    ///
+    /// 用于接入在本工具之前已经存在表结构的数据库。历史表已有记录时需要 `force`，
+    /// 此时已成功应用的版本会被跳过。
+    pub async fn baseline(&self, version: &str, description: &str, force: bool) -> Result<Vec<MigrationRecord>> {
+        let target = MigrationVersion::parse_target(version)?;
+        
+        self.with_migration_lock(|| async {
+            let table_name = self.get_migration_table_name();
+            
+            let existing = self.query_single_u64(&format!("SELECT count() FROM {} FINAL", table_name)).await
+                .context("Failed to count existing migration records")?;
+            if existing > 0 && !force {
+                return Err(anyhow!(
+                    "Migration table {} already has {} records, refusing to baseline (use force to override)",
+                    table_name, existing
+                ));
+            }
+            
+            let migration_files = self.scan_migration_files().await
+                .context("Failed to scan migration files")?;
+            let applied_versions = self.get_applied_versions().await
+                .context("Failed to get applied versions")?;
+            
+            let to_baseline: Vec<&MigrationFile> = migration_files.values()
+                .filter(|m| m.version().map(|v| v.number <= target.number).unwrap_or(false))
+                .filter(|m| !applied_versions.contains(&m.version))
+                .collect();
+            
+            if to_baseline.is_empty() {
+                return Err(anyhow!("No unapplied migration files at or below version {}", target.original));
+            }
+            
+            let run = RunContext::new();
+            let note = format!("baseline: {}", description);
+            let mut records = Vec::with_capacity(to_baseline.len());
+            
+            for migration in to_baseline {
+                let now = Utc::now();
+                let record = MigrationRecord {
+                    run_id: run.run_id.clone(),
+                    version: migration.version.clone(),
+                    attempt: self.next_attempt_number(&migration.version).await?,
+                    name: migration.name.clone(),
+                    checksum: migration.checksum.clone(),
+                    success: true,
+                    error_message: String::new(),
+                    execution_time_ms: 0,
+                    started_at: now,
+                    finished_at: now,
+                    hostname: run.hostname.clone(),
+                    os_user: run.os_user.clone(),
+                    tool_version: super::VERSION.to_string(),
+                    statements_total: 0,
+                    statements_completed: 0,
+                    note: note.clone(),
+                    query_ids: Vec::new(),
+                    read_rows: 0,
+                    read_bytes: 0,
+                    memory_usage: 0,
+                    query_duration_ms: 0,
+                };
+                
+                self.save_migration_record(&record).await
+                    .with_context(|| format!("Failed to record baseline for migration {}", migration.version))?;
+                info!("Baselined migration {} - {}", migration.version, migration.name);
+                records.push(record);
+            }
+            
+            info!("Baselined {} migrations up to version {}", records.len(), target.original);
+            Ok(records)
+        }).await
+    }
+    
+    /// 修复历史表：更新已确认修改的文件的校验和，清理之后已成功版本的失败尝试记录
+    ///
+    /// `options.confirm` 为 false 时只返回预览，不做任何修改。
+    pub async fn repair(&self, options: &RepairOptions) -> Result<RepairReport> {
+        if !options.confirm {
+            return self.plan_repair(options).await;
+        }
+        
+        self.with_migration_lock(|| async {
+            let mut report = self.plan_repair(options).await?;
+            let table_name = self.get_migration_table_name();
+            let client = self.connection_manager.get_client();
+            
+            for action in &report.actions {
+                match action {
+                    RepairAction::UpdateChecksum { version, new_checksum, .. } => {
+                        client.query(&format!(
+                                "ALTER TABLE {} UPDATE checksum = ? WHERE version = ? AND success = 1", 
+                                table_name
+                            ))
+                            .bind(new_checksum.as_str())
+                            .bind(version.as_str())
+                            .with_option("mutations_sync", "2")
+                            .execute().await
+                            .with_context(|| format!("Failed to update checksum for migration {}", version))?;
+                    }
+                    RepairAction::PurgeFailedAttempts { version, attempts } => {
+                        client.query(&format!(
+                                "DELETE FROM {} WHERE version = ? AND success = 0 AND has(?, attempt)", 
+                                table_name
+                            ))
+                            .bind(version.as_str())
+                            .bind(attempts)
+                            .execute().await
+                            .with_context(|| format!("Failed to purge failed attempts for migration {}", version))?;
+                    }
+                }
+                info!("Repair: {}", action);
+            }
+            
+            report.applied = true;
+            Ok(report)
+        }).await
+    }
+    
+    /// 计算修复需要的修改
+    async fn plan_repair(&self, options: &RepairOptions) -> Result<RepairReport> {
+        let table_name = self.get_migration_table_name();
+        let mut report = RepairReport::default();
+        
+        if !self.table_exists(&table_name).await? {
+            return Ok(report);
+        }
+        
+        let migration_files = self.scan_migration_files().await
+            .context("Failed to scan migration files")?;
+        let (mismatches, _) = self.check_applied_checksums(&migration_files).await?;
+        
+        let acknowledged: Vec<u32> = options.acknowledged_versions.iter()
+            .map(|v| MigrationVersion::parse_target(v).map(|v| v.number))
+            .collect::<Result<_>>()?;
+        
+        for mismatch in mismatches {
+            let number = MigrationVersion::parse(&mismatch.version)?.number;
+            if options.acknowledge_all || acknowledged.contains(&number) {
+                report.actions.push(RepairAction::UpdateChecksum {
+                    version: mismatch.version,
+                    old_checksum: mismatch.stored_checksum,
+                    new_checksum: mismatch.file_checksum,
+                });
+            } else {
+                report.unacknowledged_mismatches.push(mismatch);
+            }
+        }
+        
+        let query = format!(
+            "SELECT f.version AS version, groupArray(f.attempt) AS attempts
+             FROM {table} AS f FINAL
+             INNER JOIN (
+                 SELECT version, max(finished_at) AS succeeded_at 
+                 FROM {table} FINAL WHERE success = 1 GROUP BY version
+             ) AS s ON f.version = s.version
+             WHERE f.success = 0 AND f.finished_at < s.succeeded_at
+             GROUP BY f.version
+             ORDER BY f.version",
+            table = table_name
+        );
+        
+        let failed_attempts = self.connection_manager.get_client()
+            .query(&query)
+            .fetch_all::<FailedAttemptsRow>()
+            .await
+            .context("Failed to query superseded failed attempts")?;
+        
+        for FailedAttemptsRow { version, mut attempts } in failed_attempts {
+            attempts.sort_unstable();
+            report.actions.push(RepairAction::PurgeFailedAttempts { version, attempts });
+        }
+        
+        Ok(report)
     }
     
     /// 获取迁移状态
@@ -819,7 +2021,10 @@ impl SimpleMigrator {
         let table_exists = self.table_exists(&table_name).await?;
         
         let (total_migrations, last_migration) = if table_exists {
-            let count_query = format!("SELECT count() FROM {} WHERE success = 1", table_name);
+            let count_query = format!(
+                "SELECT uniqExact(version) FROM {} FINAL WHERE success = 1 AND {}", 
+                table_name, VERSIONED_ONLY
+            );
             let count = match self.query_single_u64(&count_query).await {
                 Ok(c) => c as usize,
                 Err(e) => {
@@ -829,8 +2034,8 @@ impl SimpleMigrator {
             };
             
             let last_query = format!(
-                "SELECT version FROM {} WHERE success = 1 ORDER BY version DESC LIMIT 1", 
-                table_name
+                "SELECT version FROM {} FINAL WHERE success = 1 AND {} ORDER BY version DESC LIMIT 1", 
+                table_name, VERSIONED_ONLY
             );
             let last = match self.query_single_string(&last_query).await {
                 Ok(version) => Some(version),
@@ -845,12 +2050,21 @@ impl SimpleMigrator {
             (0, None)
         };
         
+        let repeatable = match self.scan_all_migrations().await {
+            Ok(scanned) => self.get_repeatable_status(scanned.repeatable).await?,
+            Err(e) => {
+                warn!("Failed to scan repeatable migrations: {}", e);
+                Vec::new()
+            }
+        };
+        
         Ok(MigrationStatus {
             service_name: self.service_name.clone(),
             migrations_table: table_name,
             total_migrations,
             table_exists,
             last_migration,
+            repeatable,
         })
     }
     
@@ -863,36 +2077,16 @@ impl SimpleMigrator {
         }
         
         let query = format!(
-            "SELECT version, name, applied_at, execution_time_ms, checksum, success, error_message 
-             FROM {} WHERE success = 0 ORDER BY applied_at DESC",
+            "SELECT ?fields FROM {} FINAL WHERE success = 0 ORDER BY finished_at DESC",
             table_name
         );
         
-        let records = match self.query_migration_records(&query).await {
-            Ok(records) => records,
-            Err(e) => {
-                warn!("Failed to query failed migrations: {}", e);
-                return Ok(Vec::new());
-            }
-        };
-        
-        let migrations = records.into_iter().map(|(version, name, applied_at, execution_time_ms, checksum, success, error_message)| {
-            MigrationRecord {
-                version,
-                name,
-                applied_at,
-                execution_time_ms,
-                checksum,
-                success: success == 1,
-                error_message,
-            }
-        }).collect();
-        
-        Ok(migrations)
+        self.query_migration_records(&query, &[]).await
+            .context("Failed to query failed migrations")
     }
     
-    /// 获取迁移执行的详细日志
-    pub async fn get_migration_logs(&self, version: &str) -> Result<Vec<String>> {
+    /// 获取某个版本的所有执行尝试（按尝试次数排序）
+    pub async fn get_migration_logs(&self, version: &str) -> Result<Vec<MigrationRecord>> {
         let table_name = self.get_migration_table_name();
         
         if !self.table_exists(&table_name).await? {
@@ -900,30 +2094,12 @@ impl SimpleMigrator {
         }
         
         let query = format!(
-            "SELECT error_message, applied_at, execution_time_ms 
-             FROM {} WHERE version = '{}' ORDER BY applied_at DESC",
-            table_name, version
+            "SELECT ?fields FROM {} FINAL WHERE version = ? ORDER BY attempt",
+            table_name
         );
         
-        // 尝试获取实际的迁移日志
-        match self.query_migration_records(&query).await {
-            Ok(records) => {
-                let logs = records.into_iter()
-                    .map(|(_, _, applied_at, execution_time_ms, _, _, error_message)| {
-                        format!("Migration {} executed at {} ({}ms): {}", 
-                               version, applied_at, execution_time_ms, 
-                               if error_message.is_empty() { "Success".to_string() } else { error_message })
-                    })
-                    .collect();
-                Ok(logs)
-            }
-            Err(e) => {
-                warn!("Failed to query migration logs for version {}: {}", version, e);
-                // 返回一个简化的日志格式作为后备
-                Ok(vec![format!("Migration {} executed at {} (no detailed logs available)", 
-                                version, chrono::Utc::now())])
-            }
-        }
+        self.query_migration_records(&query, &[version]).await
+            .with_context(|| format!("Failed to query migration logs for version {}", version))
     }
     
     /// 获取已应用迁移的详细记录
@@ -935,80 +2111,221 @@ impl SimpleMigrator {
         }
         
         let query = format!(
-            "SELECT version, name, applied_at, execution_time_ms, checksum, success, error_message 
-             FROM {} ORDER BY version",
+            "SELECT ?fields FROM {} FINAL ORDER BY version, attempt",
             table_name
         );
         
-        let records = match self.query_migration_records(&query).await {
-            Ok(records) => records,
-            Err(e) => {
-                warn!("Failed to query applied migrations: {}", e);
+        self.query_migration_records(&query, &[]).await
+            .context("Failed to query applied migrations")
+    }
+    
+    /// 回滚最后一个迁移（如果支持，持有迁移锁期间执行）
+    pub async fn rollback_last(&self) -> Result<()> {
+        self.with_migration_lock(|| async {
+            // 获取最后一个成功的迁移
+            let table_name = self.get_migration_table_name();
+            let query = format!(
+                "SELECT version FROM {} FINAL WHERE success = 1 AND {} ORDER BY version DESC LIMIT 1",
+                table_name, VERSIONED_ONLY
+            );
+            
+            let last_version = self.query_single_string(&query).await
+                .context("No migrations to rollback")?;
+            
+            // 扫描迁移文件找到对应的回滚SQL
+            let migration_files = self.scan_migration_files().await?;
+            
+            self.rollback_versions(&migration_files, &[last_version]).await?;
+            Ok(())
+        }).await
+    }
+    
+    /// 回滚所有版本号大于 `target_version` 的已应用迁移（按版本降序），返回被回滚的版本
+    ///
+    /// 执行任何回滚SQL之前会先检查范围内的每个迁移都有对应文件和 Down 部分。
+    pub async fn rollback_to(&self, target_version: &str) -> Result<Vec<String>> {
+        let target = MigrationVersion::parse_target(target_version)?;
+        
+        self.with_migration_lock(|| async {
+            let migration_files = self.scan_migration_files().await
+                .context("Failed to scan migration files")?;
+            
+            let applied_versions = self.get_applied_versions().await
+                .context("Failed to get applied versions")?;
+            
+            let mut to_rollback: Vec<MigrationVersion> = applied_versions.iter()
+                .map(|v| MigrationVersion::parse(v))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .filter(|v| v.number > target.number)
+                .collect();
+            to_rollback.sort_by(|a, b| b.cmp(a));
+            
+            if to_rollback.is_empty() {
+                info!("No applied migrations above version {}, nothing to roll back", target.original);
                 return Ok(Vec::new());
             }
-        };
-        
-        let migrations = records.into_iter().map(|(version, name, applied_at, execution_time_ms, checksum, success, error_message)| {
-            MigrationRecord {
-                version,
-                name,
-                applied_at,
-                execution_time_ms,
-                checksum,
-                success: success == 1,
-                error_message,
-            }
-        }).collect();
-        
-        Ok(migrations)
+            
+            let versions: Vec<String> = to_rollback.into_iter().map(|v| v.original).collect();
+            info!("Rolling back {} migrations down to version {}: {:?}", 
+                  versions.len(), target.original, versions);
+            
+            self.rollback_versions(&migration_files, &versions).await?;
+            Ok(versions)
+        }).await
     }
     
-    /// 回滚最后一个迁移（如果支持）
-    pub async fn rollback_last(&self) -> Result<()> {
-        // 获取最后一个成功的迁移
+    /// 按给定顺序回滚迁移：先整体检查回滚SQL是否齐全，再逐个执行并删除迁移记录
+    async fn rollback_versions(
+        &self, 
+        migration_files: &BTreeMap<String, MigrationFile>, 
+        versions: &[String]
+    ) -> Result<()> {
         let table_name = self.get_migration_table_name();
-        let query = format!(
-            "SELECT version FROM {} WHERE success = 1 ORDER BY version DESC LIMIT 1",
-            table_name
-        );
-        
-        let last_version = self.query_single_string(&query).await
-            .context("No migrations to rollback")?;
         
-        // 扫描迁移文件找到对应的回滚SQL
-        let migration_files = self.scan_migration_files().await?;
+        let mut rollback_plan = Vec::with_capacity(versions.len());
+        for version in versions {
+            let migration_file = migration_files.get(version)
+                .ok_or_else(|| anyhow!("Migration file for version {} not found", version))?;
+            
+            if migration_file.is_baseline || migration_file.code.is_some() {
+                rollback_plan.push((migration_file, None));
+                continue;
+            }
+            
+            match &migration_file.down_sql {
+                Some(down_sql) if !down_sql.trim().is_empty() => {
+                    rollback_plan.push((migration_file, Some(down_sql)));
+                }
+                _ => return Err(anyhow!("Migration {} does not support rollback", version)),
+            }
+        }
         
-        if let Some(migration_file) = migration_files.get(&last_version) {
-            if let Some(down_sql) = &migration_file.down_sql {
-                info!("Rolling back migration: {} - {}", migration_file.version, migration_file.name);
-                
-                // 执行回滚SQL
-                self.execute_sql_statements(down_sql).await
-                    .context("Failed to execute rollback SQL")?;
-                
-                // 删除迁移记录
-                let delete_sql = format!(
-                    "DELETE FROM {} WHERE version = '{}'",
-                    table_name, last_version
-                );
-                self.execute_ddl(&delete_sql).await?;
-                
-                info!("Successfully rolled back migration: {}", last_version);
-            } else {
-                return Err(anyhow!("Migration {} does not support rollback", last_version));
+        for (migration_file, down_sql) in rollback_plan {
+            info!("Rolling back migration: {} - {}", migration_file.version, migration_file.name);
+            
+            // 执行回滚SQL或代码迁移的 down（基线迁移只删除记录）
+            if let Some(code) = &migration_file.code {
+                code.migration().down(&self.connection_manager.get_client()).await
+                    .with_context(|| format!("Failed to execute rollback for Rust migration {}", migration_file.version))?;
+            } else if let Some(down_sql) = down_sql {
+                self.execute_sql_statements(
+                    down_sql, 
+                    &migration_file.down_source, 
+                    &migration_file.directives, 
+                    &mut StatementProgress::default(),
+                    None
+                ).await
+                    .with_context(|| format!("Failed to execute rollback SQL for migration {}", migration_file.version))?;
             }
-        } else {
-            return Err(anyhow!("Migration file for version {} not found", last_version));
+            
+            // 删除迁移记录
+            self.connection_manager.get_client()
+                .query(&format!("DELETE FROM {} WHERE version = ?", table_name))
+                .bind(&migration_file.version)
+                .execute().await
+                .with_context(|| format!("Failed to delete history records for migration {}", migration_file.version))?;
+            
+            info!("Successfully rolled back migration: {}", migration_file.version);
         }
         
         Ok(())
     }
+    
+    /// 在迁移目录中创建下一个版本的迁移文件（带 Up/Down 部分的模板），返回文件路径
+    ///
+    /// 版本号为现有迁移（文件和代码迁移）和历史表中已应用版本的最大版本号加一，位数与现有版本一致。
+    /// 本地迁移目录落后于数据库时也不会与已应用的版本冲突；文件已存在时返回错误。
+    pub async fn create_migration(&self, name: &str) -> Result<std::path::PathBuf> {
+        use tokio::io::AsyncWriteExt;
+        
+        let MigrationSource::Directory(migrations_path) = &self.source else {
+            return Err(anyhow!("Cannot create migration files for {}", self.source));
+        };
+        
+        let mut migration_files = if std::path::Path::new(migrations_path).exists() {
+            self.scan_migration_directory(migrations_path).await?
+        } else {
+            BTreeMap::new()
+        };
+        migration_files.retain(|_, migration| !migration.repeatable);
+        
+        self.add_code_migrations(&mut migration_files)?;
+        
+        // 已应用的版本可能没有本地文件（本地迁移目录落后于数据库），同样参与计算
+        let applied = self.get_applied_versions().await?;
+        let versions: Vec<MigrationVersion> = migration_files.values()
+            .filter_map(|m| m.version().ok())
+            .chain(applied.iter().filter_map(|v| MigrationVersion::parse(v).ok()))
+            .collect();
+        
+        let width = versions.iter().map(|v| v.original.len()).max().unwrap_or(0).max(scaffold::MIN_VERSION_WIDTH);
+        let next = versions.iter().map(|v| v.number).max().unwrap_or(0) + 1;
+        let version = format!("{:0width$}", next, width = width);
+        
+        let slug = scaffold::slugify(name);
+        let file_name = format!("V{}__{}.sql", version, slug);
+        let path = std::path::Path::new(migrations_path).join(&file_name);
+        
+        tokio::fs::create_dir_all(migrations_path).await
+            .with_context(|| format!("Failed to create migrations directory {}", migrations_path))?;
+        
+        // create_new：文件已存在时失败，不覆盖
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        let mut file = options.open(&path).await
+            .with_context(|| format!("Failed to create migration file {}", path.display()))?;
+        file.write_all(scaffold::template(&file_name, name, &slug).as_bytes()).await
+            .with_context(|| format!("Failed to write migration file {}", path.display()))?;
+        
+        info!("Created migration {}", path.display());
+        Ok(path)
+    }
+    
+    /// 查询当前迁移锁的持有者
+    pub async fn get_lock_holder(&self) -> Result<Option<LockInfo>> {
+        if !self.table_exists(&self.get_lock_table_name()).await? {
+            return Ok(None);
+        }
+        self.lock_table().current_holder().await
+    }
+    
+    /// 强制释放迁移锁（管理操作，仅在确认持有者已不存在时使用）
+    pub async fn force_unlock(&self) -> Result<Option<LockInfo>> {
+        self.lock_table().force_unlock().await
+    }
+}
+
+/// 语句执行进度
+#[derive(Debug, Default, Clone)]
+struct StatementProgress {
+    total: u32,
+    completed: u32,
+    /// (语句序号, query_id)，包括失败的语句
+    query_ids: Vec<(usize, String)>,
+}
+
+/// 成功执行的迁移及其执行结果
+struct ExecutedMigration {
+    record: MigrationRecord,
+    ddl_results: Vec<DdlStatementResult>,
+    statement_stats: Vec<StatementStats>,
+}
+
+/// 最近一次尝试失败但已完成部分语句的迁移
+#[derive(Debug, Row, Deserialize)]
+struct PartialMigrationRow {
+    version: String,
+    checksum: String,
+    completed: u32,
+    total: u32,
 }
 
-#[derive(Debug)]
-enum Section {
-    Up,
-    Down,
+/// 某个版本的失败尝试编号
+#[derive(Debug, Row, Deserialize)]
+struct FailedAttemptsRow {
+    version: String,
+    attempts: Vec<u32>,
 }
 
 impl MigrationSummary {
@@ -1017,6 +2334,8 @@ impl MigrationSummary {
             successful: Vec::new(),
             failed: Vec::new(),
             total_time: std::time::Duration::default(),
+            ddl_results: Vec::new(),
+            statement_stats: Vec::new(),
         }
     }
     
@@ -1037,6 +2356,36 @@ impl MigrationSummary {
     }
 }
 
+impl std::fmt::Display for MigrationRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Migration {} attempt {} (run {}) on {} by {} at {} ({}ms, statements {}/{}): {}",
+            self.version,
+            self.attempt,
+            self.run_id,
+            if self.hostname.is_empty() { "-" } else { &self.hostname },
+            if self.os_user.is_empty() { "-" } else { &self.os_user },
+            self.started_at.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.execution_time_ms,
+            self.statements_completed,
+            self.statements_total,
+            if self.success { "Success" } else { &self.error_message }
+        )?;
+        if !self.note.is_empty() {
+            write!(f, " [{}]", self.note)?;
+        }
+        if !self.query_ids.is_empty() {
+            write!(
+                f, 
+                " (read {} rows / {} bytes, peak memory {} bytes, server time {}ms)",
+                self.read_rows, self.read_bytes, self.memory_usage, self.query_duration_ms
+            )?;
+        }
+        Ok(())
+    }
+}
+
 impl std::fmt::Display for MigrationStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Migration Status for service: {}", self.service_name)?;
@@ -1046,10 +2395,26 @@ impl std::fmt::Display for MigrationStatus {
         if let Some(ref last) = self.last_migration {
             writeln!(f, "  Last migration: {}", last)?;
         }
+        if !self.repeatable.is_empty() {
+            writeln!(f, "  Repeatable migrations:")?;
+            for migration in &self.repeatable {
+                writeln!(f, "    - {} ({})", migration.file_name, migration.state)?;
+            }
+        }
         Ok(())
     }
 }
 
+impl std::fmt::Display for RepeatableState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepeatableState::Pending => write!(f, "pending"),
+            RepeatableState::Outdated => write!(f, "outdated"),
+            RepeatableState::UpToDate => write!(f, "up to date"),
+        }
+    }
+}
+
 impl std::fmt::Display for MigrationSummary {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Migration Summary:")?;
@@ -1064,6 +2429,32 @@ impl std::fmt::Display for MigrationSummary {
             }
         }
         
+        if !self.ddl_results.is_empty() {
+            writeln!(f, "\nDistributed DDL results:")?;
+            for result in &self.ddl_results {
+                writeln!(f, "  - {} statement {}: {}", result.version, result.statement_index, result.statement)?;
+                for host in &result.hosts {
+                    if host.status == 0 {
+                        writeln!(f, "      {}:{} OK", host.host, host.port)?;
+                    } else {
+                        writeln!(f, "      {}:{} status {}: {}", host.host, host.port, host.status, host.error)?;
+                    }
+                }
+            }
+        }
+        
+        if !self.statement_stats.is_empty() {
+            writeln!(f, "\nStatement statistics (system.query_log):")?;
+            for stats in &self.statement_stats {
+                writeln!(
+                    f, 
+                    "  - {} statement {}: {}ms, read {} rows / {} bytes, peak memory {} bytes [{}]",
+                    stats.version, stats.statement_index, stats.duration_ms, 
+                    stats.read_rows, stats.read_bytes, stats.memory_usage, stats.query_id
+                )?;
+            }
+        }
+        
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,575 @@
+//! 分层配置：TOML 配置文件（按环境分组）→ 环境变量 → 命令行参数，后者覆盖前者。
+//!
+//! ```toml
+//! default_environment = "development"
+//!
+//! [environments.development]
+//! url = "http://localhost:8123"
+//! database = "default"
+//! user = "default"
+//! password_env = "CLICKHOUSE_PASSWORD"
+//! schema_file = "schema.sql"
+//!
+//! [environments.production]
+//! url = "https://clickhouse.internal:8443"
+//! database = "analytics"
+//! user = "migrator"
+//! password_file = "/run/secrets/clickhouse_password"
+//! cluster = "prod_cluster"
+//!
+//! [environments.production.placeholders]
+//! ttl_days = "365"
+//!
+//! [environments.production.lint]
+//! CH001 = "error"
+//! create-without-if-not-exists = "off"
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::clickhouse_migrator::{placeholders, LintConfig, MigratorConfig};
+use crate::clickhouse_migrator::directives::parse_duration;
+
+/// 未指定配置文件时，在当前目录查找的默认配置文件
+pub const DEFAULT_CONFIG_FILE: &str = "clickhouse.toml";
+/// 未指定环境且配置文件中没有 `default_environment` 时使用的环境名
+pub const DEFAULT_ENVIRONMENT: &str = "development";
+
+/// 一层配置：配置文件中的一个环境、环境变量或命令行参数，未设置的项为 None
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub url: Option<String>,
+    pub database: Option<String>,
+    pub user: Option<String>,
+    /// 明文密码，建议改用 `password_file` 或 `password_env`
+    pub password: Option<String>,
+    /// 从文件读取密码（去掉末尾换行）
+    pub password_file: Option<PathBuf>,
+    /// 从指定的环境变量读取密码
+    pub password_env: Option<String>,
+    pub service_name: Option<String>,
+    pub migrations_path: Option<String>,
+    pub history_table: Option<String>,
+    pub cluster: Option<String>,
+    /// 迁移成功后写入数据库结构快照的文件，如 `schema.sql`
+    pub schema_file: Option<String>,
+    pub continue_on_failure: Option<bool>,
+    pub validate_checksums: Option<bool>,
+    pub concurrent_file_scan: Option<bool>,
+    /// 存在结构漂移时仍然执行迁移
+    pub allow_drift: Option<bool>,
+    pub wait_for_mutations: Option<bool>,
+    /// 等待 mutation 的最长时间，如 `30m`
+    pub mutation_timeout: Option<String>,
+    /// 迁移 SQL 中 `${name}` 占位符的值，按名称逐项覆盖下层
+    #[serde(default)]
+    pub placeholders: BTreeMap<String, String>,
+    /// 静态检查规则（代码或名称）的级别：off、warning、error，按规则逐项覆盖下层
+    #[serde(default)]
+    pub lint: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    default_environment: Option<String>,
+    #[serde(default)]
+    environments: BTreeMap<String, ConfigLayer>,
+}
+
+impl ConfigLayer {
+    /// 从环境变量读取配置层
+    ///
+    /// 连接相关变量统一使用 `CLICKHOUSE_` 前缀；迁移选项沿用 `MigratorConfig::from_env` 的变量名。
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            url: env_string("CLICKHOUSE_URL"),
+            database: env_string("CLICKHOUSE_DATABASE"),
+            user: env_string("CLICKHOUSE_USER"),
+            password: env_string("CLICKHOUSE_PASSWORD"),
+            password_file: env_string("CLICKHOUSE_PASSWORD_FILE").map(PathBuf::from),
+            password_env: None,
+            service_name: env_string("CLICKHOUSE_SERVICE"),
+            migrations_path: env_string("CLICKHOUSE_MIGRATIONS_PATH"),
+            history_table: env_string("CLICKHOUSE_HISTORY_TABLE"),
+            cluster: env_string("CLICKHOUSE_CLUSTER"),
+            schema_file: env_string("CLICKHOUSE_SCHEMA_FILE"),
+            continue_on_failure: env_bool("CONTINUE_ON_MIGRATION_FAILURE")?,
+            validate_checksums: env_bool("VALIDATE_MIGRATION_CHECKSUMS")?,
+            concurrent_file_scan: env_bool("CONCURRENT_FILE_SCAN")?,
+            allow_drift: env_bool("ALLOW_SCHEMA_DRIFT")?,
+            wait_for_mutations: env_bool("WAIT_FOR_MUTATIONS")?,
+            mutation_timeout: env_string("MUTATION_TIMEOUT"),
+            placeholders: placeholders::from_env(),
+            lint: BTreeMap::new(),
+        })
+    }
+
+    /// 用 `other` 中已设置的项覆盖当前层
+    ///
+    /// 密码来源作为一个整体覆盖：上层设置了任意一种密码来源时，下层的密码来源全部失效。
+    pub fn merge(self, other: ConfigLayer) -> ConfigLayer {
+        let other_has_password = other.password.is_some()
+            || other.password_file.is_some()
+            || other.password_env.is_some();
+        let (password, password_file, password_env) = if other_has_password {
+            (other.password, other.password_file, other.password_env)
+        } else {
+            (self.password, self.password_file, self.password_env)
+        };
+
+        let mut merged_placeholders = self.placeholders;
+        merged_placeholders.extend(other.placeholders);
+        let mut merged_lint = self.lint;
+        merged_lint.extend(other.lint);
+
+        ConfigLayer {
+            url: other.url.or(self.url),
+            database: other.database.or(self.database),
+            user: other.user.or(self.user),
+            password,
+            password_file,
+            password_env,
+            service_name: other.service_name.or(self.service_name),
+            migrations_path: other.migrations_path.or(self.migrations_path),
+            history_table: other.history_table.or(self.history_table),
+            cluster: other.cluster.or(self.cluster),
+            schema_file: other.schema_file.or(self.schema_file),
+            continue_on_failure: other.continue_on_failure.or(self.continue_on_failure),
+            validate_checksums: other.validate_checksums.or(self.validate_checksums),
+            concurrent_file_scan: other.concurrent_file_scan.or(self.concurrent_file_scan),
+            allow_drift: other.allow_drift.or(self.allow_drift),
+            wait_for_mutations: other.wait_for_mutations.or(self.wait_for_mutations),
+            mutation_timeout: other.mutation_timeout.or(self.mutation_timeout),
+            placeholders: merged_placeholders,
+            lint: merged_lint,
+        }
+    }
+}
+
+fn env_string(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn env_bool(name: &str) -> Result<Option<bool>> {
+    match env_string(name) {
+        Some(value) => match value.trim() {
+            "true" => Ok(Some(true)),
+            "false" => Ok(Some(false)),
+            other => Err(anyhow!("Invalid value for {}: {} (expected true or false)", name, other)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// 合并并验证后的最终配置
+#[derive(Clone)]
+pub struct ClickHouseConfig {
+    /// 使用的环境名
+    pub environment: String,
+    /// 读取的配置文件（未使用配置文件时为 None）
+    pub config_file: Option<PathBuf>,
+    pub url: String,
+    pub database: String,
+    pub user: String,
+    pub password: String,
+    pub service_name: String,
+    pub migrations_path: String,
+    pub history_table: Option<String>,
+    pub cluster: Option<String>,
+    pub migrator: MigratorConfig,
+    pub placeholders: BTreeMap<String, String>,
+}
+
+impl std::fmt::Debug for ClickHouseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClickHouseConfig")
+            .field("environment", &self.environment)
+            .field("config_file", &self.config_file)
+            .field("url", &self.url)
+            .field("database", &self.database)
+            .field("user", &self.user)
+            .field("password", &"***")
+            .field("service_name", &self.service_name)
+            .field("migrations_path", &self.migrations_path)
+            .field("history_table", &self.history_table)
+            .field("cluster", &self.cluster)
+            .field("migrator", &self.migrator)
+            .field("placeholders", &self.placeholders)
+            .finish()
+    }
+}
+
+impl std::fmt::Display for ClickHouseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Configuration:")?;
+        writeln!(f, "  Environment: {}", self.environment)?;
+        match &self.config_file {
+            Some(path) => writeln!(f, "  Config file: {}", path.display())?,
+            None => writeln!(f, "  Config file: (none)")?,
+        }
+        writeln!(f, "  URL: {}", self.url)?;
+        writeln!(f, "  Database: {}", self.database)?;
+        writeln!(f, "  User: {}", self.user)?;
+        writeln!(f, "  Password: {}", if self.password.is_empty() { "(empty)" } else { "***" })?;
+        writeln!(f, "  Service: {}", self.service_name)?;
+        writeln!(f, "  Migrations path: {}", self.migrations_path)?;
+        if let Some(history_table) = &self.history_table {
+            writeln!(f, "  History table: {}", history_table)?;
+        }
+        if let Some(cluster) = &self.cluster {
+            writeln!(f, "  Cluster: {}", cluster)?;
+        }
+        if let Some(schema_file) = &self.migrator.schema_file {
+            writeln!(f, "  Schema file: {}{}", schema_file, if self.migrator.allow_drift { " (drift allowed)" } else { "" })?;
+        }
+        for (name, value) in &self.placeholders {
+            writeln!(f, "  Placeholder ${{{}}}: {}", name, value)?;
+        }
+        write!(
+            f,
+            "  Continue on failure: {}, validate checksums: {}, concurrent file scan: {}",
+            self.migrator.continue_on_failure,
+            self.migrator.validate_checksums,
+            self.migrator.concurrent_file_scan
+        )?;
+        if self.migrator.wait_for_mutations {
+            write!(f, "\n  Mutation timeout: {:?}", self.migrator.mutation_timeout)
+        } else {
+            write!(f, "\n  Waiting for mutations: disabled")
+        }
+    }
+}
+
+/// 配置加载器
+///
+/// 配置文件查找顺序：`config_path()` → `CLICKHOUSE_CONFIG` → 当前目录的 `clickhouse.toml`（可选）。
+/// 环境名查找顺序：`environment()` → `CLICKHOUSE_ENV` → 配置文件的 `default_environment` → `development`。
+#[derive(Debug, Default)]
+pub struct ConfigLoader {
+    config_path: Option<PathBuf>,
+    environment: Option<String>,
+    overrides: ConfigLayer,
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    pub fn environment(mut self, environment: &str) -> Self {
+        self.environment = Some(environment.to_string());
+        self
+    }
+
+    /// 最高优先级的配置层（通常来自命令行参数）
+    pub fn overrides(mut self, overrides: ConfigLayer) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// 读取并合并所有配置层，验证通过后返回最终配置
+    pub fn load(self) -> Result<ClickHouseConfig> {
+        let explicit_path = self.config_path.or_else(|| env_string("CLICKHOUSE_CONFIG").map(PathBuf::from));
+        let config_file = match explicit_path {
+            Some(path) => Some(path),
+            None => Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()),
+        };
+
+        let file = match &config_file {
+            Some(path) => read_config_file(path)?,
+            None => ConfigFile::default(),
+        };
+
+        let requested_environment = self.environment.or_else(|| env_string("CLICKHOUSE_ENV"));
+        let environment = requested_environment.clone()
+            .or(file.default_environment.clone())
+            .unwrap_or_else(|| DEFAULT_ENVIRONMENT.to_string());
+
+        let mut environments = file.environments;
+        let file_layer = match environments.remove(&environment) {
+            Some(layer) => layer,
+            None if requested_environment.is_some() || !environments.is_empty() => {
+                let available: Vec<&String> = environments.keys().collect();
+                return Err(anyhow!(
+                    "Environment '{}' not found in config file {} (available: {:?})",
+                    environment,
+                    config_file.as_deref().map(|p| p.display().to_string()).unwrap_or_else(|| "(none)".to_string()),
+                    available
+                ));
+            }
+            None => ConfigLayer::default(),
+        };
+
+        let layer = file_layer
+            .merge(ConfigLayer::from_env()?)
+            .merge(self.overrides);
+
+        resolve(environment, config_file, layer)
+    }
+}
+
+fn read_config_file(path: &Path) -> Result<ConfigFile> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))
+}
+
+fn is_identifier(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 填充默认值、解析密码并验证配置，所有错误一次性报告
+fn resolve(environment: String, config_file: Option<PathBuf>, layer: ConfigLayer) -> Result<ClickHouseConfig> {
+    let mut errors = Vec::new();
+    let defaults = MigratorConfig::default();
+
+    let url = layer.url.unwrap_or_else(|| "http://localhost:8123".to_string());
+    match url.split_once("://") {
+        Some(("http" | "https", host)) if !host.is_empty() => {}
+        _ => errors.push(format!("url must be an http:// or https:// URL, got '{}'", url)),
+    }
+
+    let database = layer.database.unwrap_or_else(|| "default".to_string());
+    if !is_identifier(&database) {
+        errors.push(format!("database must be a non-empty identifier, got '{}'", database));
+    }
+
+    let user = layer.user.unwrap_or_else(|| "default".to_string());
+    if user.trim().is_empty() {
+        errors.push("user must not be empty".to_string());
+    }
+
+    let service_name = layer.service_name.unwrap_or_else(|| "my_service".to_string());
+    if !is_identifier(&service_name) {
+        errors.push(format!(
+            "service_name may only contain ASCII letters, digits and '_' (it is part of the history table name), got '{}'",
+            service_name
+        ));
+    }
+
+    let migrations_path = layer.migrations_path.unwrap_or_else(|| "migrations".to_string());
+    if migrations_path.trim().is_empty() {
+        errors.push("migrations_path must not be empty".to_string());
+    }
+
+    if let Some(history_table) = &layer.history_table {
+        if !is_identifier(history_table) {
+            errors.push(format!("history_table must be an identifier, got '{}'", history_table));
+        }
+    }
+
+    if let Some(cluster) = &layer.cluster {
+        if cluster.trim().is_empty() {
+            errors.push("cluster must not be empty when set".to_string());
+        }
+    }
+
+    if let Some(schema_file) = &layer.schema_file {
+        if schema_file.trim().is_empty() {
+            errors.push("schema_file must not be empty when set".to_string());
+        }
+    }
+
+    let mutation_timeout = match &layer.mutation_timeout {
+        Some(value) => match parse_duration(value) {
+            Some(timeout) => timeout,
+            None => {
+                errors.push(format!("mutation_timeout must be a duration such as 30m or 3600s, got '{}'", value));
+                defaults.mutation_timeout
+            }
+        },
+        None => defaults.mutation_timeout,
+    };
+
+    for name in layer.placeholders.keys() {
+        if !placeholders::is_valid_name(name) {
+            errors.push(format!("invalid placeholder name '{}'", name));
+        }
+    }
+
+    let lint = LintConfig::from_map(&layer.lint).unwrap_or_else(|e| {
+        errors.push(format!("invalid lint configuration: {}", e));
+        LintConfig::default()
+    });
+
+    let password = match (layer.password, layer.password_file, layer.password_env) {
+        (None, None, None) => String::new(),
+        (Some(password), None, None) => password,
+        (None, Some(path), None) => match std::fs::read_to_string(&path) {
+            Ok(content) => content.trim_end_matches(['\r', '\n']).to_string(),
+            Err(e) => {
+                errors.push(format!("failed to read password_file {}: {}", path.display(), e));
+                String::new()
+            }
+        },
+        (None, None, Some(name)) => match std::env::var(&name) {
+            Ok(password) => password,
+            Err(_) => {
+                errors.push(format!("password_env refers to unset environment variable {}", name));
+                String::new()
+            }
+        },
+        _ => {
+            errors.push("only one of password, password_file and password_env may be set".to_string());
+            String::new()
+        }
+    };
+
+    if !errors.is_empty() {
+        return Err(anyhow!(
+            "Invalid configuration (environment '{}'):\n  - {}",
+            environment,
+            errors.join("\n  - ")
+        ));
+    }
+
+    Ok(ClickHouseConfig {
+        environment,
+        config_file,
+        url,
+        database,
+        user,
+        password,
+        service_name,
+        migrations_path,
+        history_table: layer.history_table,
+        cluster: layer.cluster,
+        migrator: MigratorConfig {
+            continue_on_failure: layer.continue_on_failure.unwrap_or(defaults.continue_on_failure),
+            validate_checksums: layer.validate_checksums.unwrap_or(defaults.validate_checksums),
+            concurrent_file_scan: layer.concurrent_file_scan.unwrap_or(defaults.concurrent_file_scan),
+            wait_for_mutations: layer.wait_for_mutations.unwrap_or(defaults.wait_for_mutations),
+            mutation_timeout,
+            lint,
+            schema_file: layer.schema_file,
+            allow_drift: layer.allow_drift.unwrap_or(defaults.allow_drift),
+        },
+        placeholders: layer.placeholders,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn resolve_layer(layer: ConfigLayer) -> Result<ClickHouseConfig> {
+        resolve("test".to_string(), None, layer)
+    }
+
+    #[test]
+    fn merge_prefers_upper_layer_and_merges_maps_per_key() {
+        let lower = ConfigLayer {
+            url: Some("http://lower:8123".to_string()),
+            database: Some("lower_db".to_string()),
+            continue_on_failure: Some(true),
+            placeholders: map(&[("env", "dev"), ("ttl_days", "30")]),
+            lint: map(&[("CH001", "error"), ("CH003", "off")]),
+            ..ConfigLayer::default()
+        };
+        let upper = ConfigLayer {
+            url: Some("http://upper:8123".to_string()),
+            continue_on_failure: Some(false),
+            placeholders: map(&[("ttl_days", "365")]),
+            lint: map(&[("CH003", "warning")]),
+            ..ConfigLayer::default()
+        };
+
+        let merged = lower.merge(upper);
+        assert_eq!(merged.url.as_deref(), Some("http://upper:8123"));
+        assert_eq!(merged.database.as_deref(), Some("lower_db"));
+        assert_eq!(merged.continue_on_failure, Some(false));
+        assert_eq!(merged.placeholders, map(&[("env", "dev"), ("ttl_days", "365")]));
+        assert_eq!(merged.lint, map(&[("CH001", "error"), ("CH003", "warning")]));
+    }
+
+    #[test]
+    fn merge_replaces_password_sources_as_a_group() {
+        let lower = ConfigLayer {
+            password: Some("secret".to_string()),
+            password_env: Some("LOWER_PASSWORD".to_string()),
+            ..ConfigLayer::default()
+        };
+
+        let merged = lower.clone().merge(ConfigLayer {
+            password_file: Some(PathBuf::from("/run/secrets/pw")),
+            ..ConfigLayer::default()
+        });
+        assert_eq!(merged.password, None);
+        assert_eq!(merged.password_env, None);
+        assert_eq!(merged.password_file, Some(PathBuf::from("/run/secrets/pw")));
+
+        // 上层没有密码来源时保留下层的
+        let merged = lower.merge(ConfigLayer::default());
+        assert_eq!(merged.password.as_deref(), Some("secret"));
+        assert_eq!(merged.password_env.as_deref(), Some("LOWER_PASSWORD"));
+    }
+
+    #[test]
+    fn resolve_applies_defaults() {
+        let config = resolve_layer(ConfigLayer::default()).unwrap();
+        assert_eq!(config.url, "http://localhost:8123");
+        assert_eq!(config.database, "default");
+        assert_eq!(config.user, "default");
+        assert_eq!(config.password, "");
+        assert_eq!(config.migrations_path, "migrations");
+    }
+
+    #[test]
+    fn resolve_reports_all_errors() {
+        let error = resolve_layer(ConfigLayer {
+            url: Some("ftp://host".to_string()),
+            database: Some("bad-name".to_string()),
+            mutation_timeout: Some("soon".to_string()),
+            lint: map(&[("CH999", "error")]),
+            ..ConfigLayer::default()
+        })
+        .unwrap_err()
+        .to_string();
+
+        assert!(error.starts_with("Invalid configuration (environment 'test'):"), "{}", error);
+        for expected in ["url must be", "database must be", "mutation_timeout must be", "invalid lint configuration"] {
+            assert!(error.contains(expected), "missing '{}' in {}", expected, error);
+        }
+    }
+
+    #[test]
+    fn resolve_rejects_conflicting_password_sources() {
+        let error = resolve_layer(ConfigLayer {
+            password: Some("secret".to_string()),
+            password_file: Some(PathBuf::from("/run/secrets/pw")),
+            ..ConfigLayer::default()
+        })
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("only one of password, password_file and password_env may be set"), "{}", error);
+    }
+
+    #[test]
+    fn password_file_trailing_newlines_are_trimmed() {
+        let path = std::env::temp_dir().join(format!("clickhouse_password_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "s3cret \r\n\n").unwrap();
+
+        let config = resolve_layer(ConfigLayer {
+            password_file: Some(path.clone()),
+            ..ConfigLayer::default()
+        });
+        std::fs::remove_file(&path).unwrap();
+
+        // 只去掉末尾换行，密码中的空格保留
+        assert_eq!(config.unwrap().password, "s3cret ");
+    }
+}
@@ -1,14 +1,17 @@
 use anyhow::Result;
 use clickhouse::Client;
 use std::sync::Arc;
+use crate::config::ClickHouseConfig;
 
 pub struct ClickHouseDB {
     client: Client,
 }
 
 /// 连接管理器，提供共享的 ClickHouse 连接
+#[derive(Clone)]
 pub struct ClickHouseConnectionManager {
     client: Arc<Client>,
+    database: String,
 }
 
 impl ClickHouseConnectionManager {
@@ -22,14 +25,25 @@ impl ClickHouseConnectionManager {
 
         Ok(Self {
             client: Arc::new(client),
+            database: database.to_string(),
         })
     }
 
+    /// 使用分层配置创建连接管理器
+    pub fn from_config(config: &ClickHouseConfig) -> Result<Self> {
+        Self::new(&config.url, &config.database, &config.user, &config.password)
+    }
+
     /// 获取共享的客户端引用
     pub fn get_client(&self) -> Arc<Client> {
         Arc::clone(&self.client)
     }
 
+    /// 连接使用的数据库名
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
     /// 创建 ClickHouseDB 实例（使用共享连接）
     pub fn create_db(&self) -> ClickHouseDB {
         ClickHouseDB {
@@ -39,12 +53,12 @@ impl ClickHouseConnectionManager {
 }
 
 impl ClickHouseDB {
-    pub fn new() -> Result<Self> {
+    pub fn new(config: &ClickHouseConfig) -> Result<Self> {
         let client = Client::default()
-            .with_url("http://localhost:8123")
-            .with_database("default")
-            .with_user("default")
-            .with_password("ClickHouse@123");
+            .with_url(&config.url)
+            .with_database(&config.database)
+            .with_user(&config.user)
+            .with_password(&config.password);
 
         Ok(Self { client })
     }
@@ -59,14 +73,14 @@ impl ClickHouseDB {
 
     pub async fn get_version(&self) -> Result<Option<String>> {
         // 使用 execute 而不是 fetch_all 来避免类型问题
-        let _result = self.client.query("SELECT version()").execute().await?;
+        self.client.query("SELECT version()").execute().await?;
         // 这里简化处理，实际项目中可能需要更复杂的逻辑
         Ok(Some("ClickHouse version".to_string()))
     }
 
     pub async fn get_tables(&self) -> Result<Vec<String>> {
         // 使用 execute 而不是 fetch_all 来避免类型问题
-        let _result = self.client.query("SHOW TABLES").execute().await?;
+        self.client.query("SHOW TABLES").execute().await?;
         // 这里简化处理，实际项目中可能需要更复杂的逻辑
         Ok(vec![])
     }
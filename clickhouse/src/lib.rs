@@ -1,7 +1,9 @@
+pub mod config;
 pub mod database;
 pub mod models;
 pub mod clickhouse_migrator;
 
 pub use database::ClickHouseDB;
+pub use config::{ClickHouseConfig, ConfigLayer, ConfigLoader};
 pub use models::*;
 pub use clickhouse_migrator::*;
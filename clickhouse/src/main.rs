@@ -1,123 +1,516 @@
 use clickhouse_connector::{
+    embed_clickhouse_migrations,
+    config::{ConfigLayer, ConfigLoader},
     database::ClickHouseConnectionManager,
-    clickhouse_migrator::SimpleMigrator,
+    clickhouse_migrator::{
+        SimpleMigrator, SimpleMigratorBuilder, MigrationRecord, MigrationSummary, RepairOptions, Severity, ValidationError, DriftError,
+    },
 };
-use std::env;
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// 进程退出码：0 表示成功
+const EXIT_FAILURE: i32 = 1;
+/// 迁移执行完成，但有迁移失败
+const EXIT_PARTIAL_FAILURE: i32 = 2;
+/// 已应用迁移的校验和与迁移文件不一致
+const EXIT_VALIDATION_FAILED: i32 = 3;
+/// 无法连接 ClickHouse
+const EXIT_CONNECTION_FAILED: i32 = 4;
+/// 静态检查发现 error 级别的问题
+const EXIT_LINT_FAILED: i32 = 5;
+/// 数据库结构与快照不一致
+const EXIT_SCHEMA_DRIFT: i32 = 6;
+
+/// 未配置 schema_file 时 `schema dump` 写入的文件
+const DEFAULT_SCHEMA_FILE: &str = "schema.sql";
+
+/// ClickHouse 数据库连接器和迁移工具
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+    
+    /// 不指定子命令时执行 migrate
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// 所有子命令共用的连接和配置参数，覆盖配置文件和环境变量中的值
+#[derive(Args)]
+struct GlobalArgs {
+    /// 显示更多调试信息
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// 输出所有日志
+    #[arg(short, long, global = true)]
+    debug: bool,
+    /// 配置文件（默认 clickhouse.toml）
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// 配置文件中的环境
+    #[arg(long, global = true)]
+    env: Option<String>,
+    /// ClickHouse HTTP 地址
+    #[arg(long, global = true)]
+    url: Option<String>,
+    /// 数据库名
+    #[arg(long, global = true)]
+    database: Option<String>,
+    /// 用户名
+    #[arg(long, global = true)]
+    user: Option<String>,
+    /// 从文件读取密码
+    #[arg(long, global = true)]
+    password_file: Option<PathBuf>,
+    /// 服务名称（历史表名为 _migrations_<服务名称>）
+    #[arg(long, global = true)]
+    service: Option<String>,
+    /// 迁移文件目录
+    #[arg(long, global = true)]
+    migrations_path: Option<String>,
+    /// 迁移历史表名
+    #[arg(long, global = true)]
+    history_table: Option<String>,
+    /// 集群名称，启用集群模式
+    #[arg(long, global = true)]
+    cluster: Option<String>,
+    /// 迁移成功后写入数据库结构快照的文件
+    #[arg(long, global = true)]
+    schema_file: Option<String>,
+    /// 迁移 SQL 中的 ${key} 占位符，可多次使用
+    #[arg(long = "placeholder", value_name = "KEY=VALUE", value_parser = parse_placeholder, global = true)]
+    placeholders: Vec<(String, String)>,
+    /// 使用编译期嵌入的 migrations/
+    #[arg(long, global = true)]
+    embedded: bool,
+    /// 输出格式；json 时只向标准输出写入一个 JSON 文档
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, global = true)]
+    output: OutputFormat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 执行待执行的迁移
+    Migrate {
+        /// 只打印迁移计划，不执行任何 SQL
+        #[arg(long, conflicts_with_all = ["resume", "target"])]
+        dry_run: bool,
+        /// 部分执行的失败迁移从第一条未完成的语句继续
+        #[arg(long, conflicts_with = "target")]
+        resume: bool,
+        /// 只执行版本号不大于该版本的迁移
+        #[arg(long, value_name = "VERSION")]
+        target: Option<String>,
+        /// 数据库结构与快照不一致时仍然执行
+        #[arg(long)]
+        allow_drift: bool,
+    },
+    /// 显示迁移状态
+    Status,
+    /// 列出每个迁移（已应用、失败、待执行）
+    Info,
+    /// 校验已应用迁移的校验和与迁移文件是否一致
+    Validate,
+    /// 回滚最后一个迁移，或回滚到指定版本
+    Rollback {
+        /// 回滚所有版本号大于该版本的迁移
+        #[arg(long, value_name = "VERSION")]
+        to: Option<String>,
+    },
+    /// 将该版本及之前的迁移标记为已应用，不执行 SQL
+    Baseline {
+        version: String,
+        #[arg(long, default_value = "existing database")]
+        description: String,
+        /// 历史表已有记录时仍然建立基线
+        #[arg(long)]
+        force: bool,
+    },
+    /// 修复校验和不一致和失败记录；不带 --confirm 时只预览
+    Repair {
+        /// 确认修改过的版本，逗号分隔；all 表示所有版本
+        #[arg(long, value_delimiter = ',', value_name = "VERSIONS")]
+        acknowledge: Vec<String>,
+        #[arg(long)]
+        confirm: bool,
+    },
+    /// 创建新的迁移文件
+    New {
+        name: String,
+    },
+    /// 显示某个版本的所有执行尝试
+    Logs {
+        version: String,
+    },
+    /// 静态检查迁移 SQL（不连接数据库）
+    Lint,
+    /// 比较数据库的实际结构和结构快照
+    Drift,
+    /// 数据库结构快照
+    Schema {
+        #[command(subcommand)]
+        command: SchemaCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum SchemaCommand {
+    /// 将表、字典和视图的定义写入快照文件
+    Dump {
+        /// 快照文件（默认使用配置的 schema_file，否则为 schema.sql）
+        path: Option<String>,
+    },
+}
+
+/// 解析 `key=value` 形式的占位符参数
+fn parse_placeholder(value: &str) -> Result<(String, String), String> {
+    value.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("invalid placeholder '{}' (expected key=value)", value))
+}
+
+/// 按输出格式打印结果和错误
+#[derive(Clone, Copy)]
+struct Output {
+    format: OutputFormat,
+    verbose: bool,
+}
+
+impl Output {
+    fn is_json(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+    
+    /// 进度和提示信息，只在 text 格式下输出
+    fn say(&self, message: impl std::fmt::Display) {
+        if !self.is_json() {
+            println!("{}", message);
+        }
+    }
+    
+    /// 命令结果：json 格式输出 `value`，text 格式调用 `text`
+    fn result<T: Serialize>(&self, value: &T, text: impl FnOnce(&T)) {
+        if self.is_json() {
+            match serde_json::to_string_pretty(value) {
+                Ok(json) => println!("{}", json),
+                Err(e) => println!("{}", json!({ "error": format!("Failed to serialize output: {}", e) })),
+            }
+        } else {
+            text(value);
+        }
+    }
+    
+    /// 打印错误，返回对应的退出码
+    fn error(&self, message: &str, e: &anyhow::Error) -> i32 {
+        self.error_with_code(message, e, exit_code(e))
+    }
+    
+    /// 打印错误，使用指定的退出码（json 格式中的 `exit_code` 与进程退出码一致）
+    fn error_with_code(&self, message: &str, e: &anyhow::Error, code: i32) -> i32 {
+        if self.is_json() {
+            println!("{}", json!({ "error": format!("{:#}", e), "exit_code": code }));
+        } else {
+            println!("❌ {}: {:#}", message, e);
+        }
+        code
+    }
+}
+
+/// 根据错误链确定退出码
+fn exit_code(e: &anyhow::Error) -> i32 {
+    let is_network_error = |cause: &(dyn std::error::Error + 'static)| {
+        matches!(cause.downcast_ref::<clickhouse::error::Error>(), Some(clickhouse::error::Error::Network(_)))
+    };
+    
+    if e.chain().any(|cause| cause.is::<ValidationError>()) {
+        EXIT_VALIDATION_FAILED
+    } else if e.chain().any(|cause| cause.is::<DriftError>()) {
+        EXIT_SCHEMA_DRIFT
+    } else if e.chain().any(is_network_error) {
+        EXIT_CONNECTION_FAILED
+    } else {
+        EXIT_FAILURE
+    }
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // 检查是否启用详细模式
-    let verbose = env::args().any(|arg| arg == "--verbose" || arg == "-v");
-    let debug_mode = env::args().any(|arg| arg == "--debug" || arg == "-d");
+    let cli = Cli::parse();
+    let global = cli.global;
+    let out = Output { format: global.output, verbose: global.verbose };
     
-    if verbose {
-        println!("🔍 启用详细模式 - 将显示更多调试信息");
+    if out.verbose {
+        out.say("🔍 启用详细模式 - 将显示更多调试信息");
     }
     
-    if debug_mode {
-        println!("🐛 启用调试模式 - 将显示所有日志信息");
-        // 设置日志级别
+    if global.debug {
+        out.say("🐛 启用调试模式 - 将显示所有日志信息");
+        // 设置日志级别；json 格式下日志写入标准错误，不影响 JSON 输出
         tracing_subscriber::fmt()
             .with_max_level(tracing::Level::DEBUG)
+            .with_writer(std::io::stderr)
             .init();
     }
     
-    println!("🚀 ClickHouse 数据库连接器和迁移工具");
+    out.say("🚀 ClickHouse 数据库连接器和迁移工具");
+    
+    // 加载配置：配置文件 → 环境变量（含 .env）→ 命令行参数
+    dotenv::dotenv().ok();
+    let allow_drift = matches!(cli.command, Some(Command::Migrate { allow_drift: true, .. }));
+    let mut loader = ConfigLoader::new().overrides(ConfigLayer {
+        url: global.url,
+        database: global.database,
+        user: global.user,
+        password_file: global.password_file,
+        service_name: global.service,
+        migrations_path: global.migrations_path,
+        history_table: global.history_table,
+        cluster: global.cluster,
+        schema_file: global.schema_file,
+        allow_drift: allow_drift.then_some(true),
+        placeholders: global.placeholders.into_iter().collect(),
+        ..ConfigLayer::default()
+    });
+    if let Some(path) = global.config {
+        loader = loader.config_path(path);
+    }
+    if let Some(environment) = global.env {
+        loader = loader.environment(&environment);
+    }
+    
+    let config = match loader.load() {
+        Ok(config) => config,
+        Err(e) => std::process::exit(out.error("配置无效", &e)),
+    };
+    
+    out.say(format!("✅ 配置加载成功（环境: {}）", config.environment));
+    if out.verbose {
+        out.say(&config);
+    }
     
     // 创建连接管理器（只创建一次连接）
-    let connection_manager = ClickHouseConnectionManager::new(
-        "http://localhost:8123",
-        "default", 
-        "default", 
-        "ClickHouse@123"
-    )?;
+    let connection_manager = match ClickHouseConnectionManager::from_config(&config) {
+        Ok(connection_manager) => connection_manager,
+        Err(e) => std::process::exit(out.error_with_code("连接管理器创建失败", &e, EXIT_CONNECTION_FAILED)),
+    };
     
-    println!("✅ 连接管理器创建成功");
+    out.say("✅ 连接管理器创建成功");
+    
+    // 使用连接管理器创建迁移器（共享同一个连接）
+    let mut builder = SimpleMigratorBuilder::from_config(&config)
+        .connection_manager(connection_manager.clone());
+    if global.embedded {
+        // 使用编译期嵌入的 migrations/，不依赖运行时的工作目录
+        builder = builder.embedded_migrations(embed_clickhouse_migrations!("migrations"));
+    }
+    
+    let command = cli.command.unwrap_or(Command::Migrate { dry_run: false, resume: false, target: None, allow_drift: false });
+    
+    // 静态检查只读取迁移文件，不连接数据库
+    if let Command::Lint = command {
+        let code = match builder.build_offline() {
+            Ok(migrator) => lint(&migrator, out).await,
+            Err(e) => out.error("迁移器创建失败", &e),
+        };
+        std::process::exit(code);
+    }
     
     // 使用连接管理器创建数据库实例
     let db = connection_manager.create_db();
     
     // 测试连接
     match db.test_connection().await {
-        Ok(true) => println!("✅ 数据库连接测试成功"),
-        Ok(false) => println!("❌ 数据库连接测试失败"),
-        Err(e) => println!("❌ 数据库连接测试出错: {}", e),
+        Ok(true) => out.say("✅ 数据库连接测试成功"),
+        Ok(false) => {
+            let e = anyhow::anyhow!("Failed to connect to ClickHouse at {}", config.url);
+            std::process::exit(out.error_with_code("数据库连接测试失败", &e, EXIT_CONNECTION_FAILED));
+        }
+        Err(e) => std::process::exit(out.error_with_code("数据库连接测试出错", &e, EXIT_CONNECTION_FAILED)),
     }
     
-    // 使用连接管理器创建迁移器
-    let migrator = SimpleMigrator::new(
-        "http://localhost:8123",
-        "my_service",
-        "migrations"
-    ).await?;
+    let migrator = match builder.build().await {
+        Ok(migrator) => migrator,
+        Err(e) => std::process::exit(out.error("迁移器创建失败", &e)),
+    };
     
-    println!("✅ 迁移器创建成功");
+    out.say("✅ 迁移器创建成功");
+    
+    let code = match command {
+        Command::Migrate { dry_run: true, .. } => plan(&migrator, out).await,
+        Command::Migrate { resume, target, .. } => {
+            if !out.is_json() {
+                print_status(&migrator, out).await;
+            }
+            let code = migrate(&migrator, resume, target.as_deref(), out).await;
+            match &config.migrator.schema_file {
+                // 只在完整迁移全部成功后更新快照；--target 只迁移到中间版本，数据库结构不是最终结构
+                Some(path) if code == 0 && target.is_none() => match write_schema(&migrator, path).await {
+                    Ok(objects) => {
+                        out.say(format!("📸 已更新数据库结构快照: {}（{} 个对象）", path, objects));
+                        0
+                    }
+                    // json 格式下标准输出已经是迁移摘要，错误写入标准错误
+                    Err(e) if out.is_json() => {
+                        eprintln!("Failed to update schema snapshot: {:#}", e);
+                        exit_code(&e)
+                    }
+                    Err(e) => out.error("更新数据库结构快照失败", &e),
+                },
+                _ => code,
+            }
+        }
+        Command::Status => print_status(&migrator, out).await,
+        Command::Info => info(&migrator, out).await,
+        Command::Validate => validate(&migrator, out).await,
+        Command::Rollback { to } => rollback(&migrator, to.as_deref(), out).await,
+        Command::Baseline { version, description, force } => {
+            // 将已有数据库标记为已应用到指定版本，不执行 SQL
+            out.say(format!("📌 建立基线: 将 V{} 及之前的迁移标记为已应用...", version));
+            match migrator.baseline(&version, &description, force).await {
+                Ok(records) => {
+                    out.result(&records, |records| {
+                        println!("✅ 基线建立成功，共标记 {} 个迁移:", records.len());
+                        for record in records {
+                            println!("  - {} - {}", record.version, record.name);
+                        }
+                    });
+                    0
+                }
+                Err(e) => out.error("建立基线失败", &e),
+            }
+        }
+        Command::Repair { acknowledge, confirm } => {
+            let options = RepairOptions {
+                acknowledge_all: acknowledge.iter().any(|v| v == "all"),
+                acknowledged_versions: acknowledge.into_iter()
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty() && v != "all")
+                    .collect(),
+                confirm,
+            };
+            
+            out.say(format!("🔧 修复迁移历史{}...", if options.confirm { "" } else { "（预览）" }));
+            match migrator.repair(&options).await {
+                Ok(report) => {
+                    out.result(&report, |report| println!("{}", report));
+                    0
+                }
+                Err(e) => out.error("修复失败", &e),
+            }
+        }
+        Command::New { name } => new_migration(&migrator, &name, out).await,
+        Command::Schema { command: SchemaCommand::Dump { path } } => {
+            let path = path.or(config.migrator.schema_file.clone()).unwrap_or_else(|| DEFAULT_SCHEMA_FILE.to_string());
+            dump_schema(&migrator, &path, out).await
+        }
+        Command::Drift => drift(&migrator, out).await,
+        Command::Lint => unreachable!("lint runs without a database connection"),
+        Command::Logs { version } => {
+            match migrator.get_migration_logs(&version).await {
+                Ok(records) => {
+                    out.result(&records, |records| {
+                        if records.is_empty() {
+                            println!("ℹ️  版本 {} 没有执行记录", version);
+                            return;
+                        }
+                        println!("📜 版本 {} 的执行记录:", version);
+                        for record in records {
+                            println!("  - {}", record);
+                        }
+                    });
+                    0
+                }
+                Err(e) => out.error("获取执行记录失败", &e),
+            }
+        }
+    };
     
-    // 获取迁移状态
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// 获取并打印迁移状态
+async fn print_status(migrator: &SimpleMigrator, out: Output) -> i32 {
     match migrator.get_migration_status().await {
         Ok(status) => {
-            println!("📊 迁移状态:");
-            println!("  服务名称: {}", status.service_name);
-            println!("  迁移表: {}", status.migrations_table);
-            println!("  已应用迁移数: {}", status.total_migrations);
+            out.result(&status, |status| {
+                println!("📊 迁移状态:");
+                println!("  服务名称: {}", status.service_name);
+                println!("  迁移表: {}", status.migrations_table);
+                println!("  已应用迁移数: {}", status.total_migrations);
+                if let Some(last) = &status.last_migration {
+                    println!("  最新版本: {}", last);
+                }
+                if !status.repeatable.is_empty() {
+                    println!("  可重复迁移:");
+                    for migration in &status.repeatable {
+                        println!("    - {} ({})", migration.file_name, migration.state);
+                    }
+                }
+            });
+            0
+        }
+        Err(e) => out.error("获取迁移状态失败", &e),
+    }
+}
+
+/// dry-run：只打印迁移计划，不执行
+async fn plan(migrator: &SimpleMigrator, out: Output) -> i32 {
+    out.say("📋 生成迁移计划（dry-run，不会执行任何 SQL）...");
+    match migrator.plan().await {
+        Ok(plan) => {
+            out.result(&plan, |plan| {
+                if plan.is_empty() {
+                    println!("✅ 没有待执行的迁移");
+                }
+                println!("{}", plan);
+                if !plan.is_executable() {
+                    println!("⚠️  存在校验和不一致的迁移，实际执行时迁移会被拒绝");
+                }
+            });
+            if plan.is_executable() { 0 } else { EXIT_VALIDATION_FAILED }
         }
-        Err(e) => println!("❌ 获取迁移状态失败: {}", e),
+        Err(e) => out.error("生成迁移计划失败", &e),
     }
+}
+
+/// 运行迁移；`resume` 时部分执行的失败迁移从未完成的语句继续
+async fn migrate(migrator: &SimpleMigrator, resume: bool, target: Option<&str>, out: Output) -> i32 {
+    let result = if let Some(target) = target {
+        out.say(format!("🔧 开始运行迁移（到版本 {}）...", target));
+        migrator.migrate_to(target).await
+    } else if resume {
+        out.say("🔧 开始运行迁移（从失败的语句继续）...");
+        migrator.resume().await
+    } else {
+        out.say("🔧 开始运行迁移...");
+        migrator.migrate().await
+    };
     
-    // 运行迁移
-    println!("🔧 开始运行迁移...");
-    match migrator.migrate().await {
+    match result {
         Ok(summary) => {
-            if summary.is_success() {
-                println!("✅ 迁移完成成功!");
-                println!("  成功迁移数: {}", summary.successful.len());
-                println!("  总耗时: {:?}", summary.total_time);
+            if out.is_json() {
+                out.result(&summary, |_| {});
             } else {
-                println!("⚠️  迁移完成，但有失败:");
-                println!("  成功迁移数: {}", summary.successful.len());
-                println!("  失败迁移数: {}", summary.failed.len());
-                println!("  总耗时: {:?}", summary.total_time);
-                
-                // 显示详细的失败信息
-                if !summary.failed.is_empty() {
-                    println!("\n❌ 失败的迁移详情:");
-                    for (i, failed) in summary.failed.iter().enumerate() {
-                        println!("  {}. 版本: {} - {}", i + 1, failed.version, failed.name);
-                        println!("     错误: {}", failed.error);
-                        println!();
-                    }
-                }
-                
-                // 显示成功的迁移信息
-                if !summary.successful.is_empty() {
-                    println!("✅ 成功的迁移:");
-                    for (i, success) in summary.successful.iter().enumerate() {
-                        println!("  {}. 版本: {} - {} (耗时: {}ms)", 
-                                i + 1, success.version, success.name, success.execution_time_ms);
-                    }
-                }
-                
-                // 在详细模式下，尝试获取更多错误信息
-                if verbose {
-                    println!("\n🔍 详细错误诊断:");
-                    match migrator.get_failed_migrations().await {
-                        Ok(failed_migrations) => {
-                            if !failed_migrations.is_empty() {
-                                println!("  数据库中的失败记录:");
-                                for failed in failed_migrations {
-                                    println!("    - {}: {} (执行时间: {}ms)", 
-                                            failed.version, failed.name, failed.execution_time_ms);
-                                    if !failed.error_message.is_empty() {
-                                        println!("      错误: {}", failed.error_message);
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => println!("  无法获取失败记录: {}", e),
-                    }
-                }
+                print_summary(migrator, &summary, out.verbose).await;
             }
+            if summary.is_success() { 0 } else { EXIT_PARTIAL_FAILURE }
         }
+        Err(e) if out.is_json() => out.error("迁移失败", &e),
         Err(e) => {
             println!("❌ 迁移失败: {}", e);
             
@@ -137,8 +530,302 @@ async fn main() -> anyhow::Result<()> {
                     break;
                 }
             }
+            exit_code(&e)
+        }
+    }
+}
+async fn print_summary(migrator: &SimpleMigrator, summary: &MigrationSummary, verbose: bool) {
+    if summary.is_success() {
+        println!("✅ 迁移完成成功!");
+        println!("  成功迁移数: {}", summary.successful.len());
+        println!("  总耗时: {:?}", summary.total_time);
+        
+        // 每条语句在 system.query_log 中的开销
+        if !summary.statement_stats.is_empty() {
+            println!("📈 语句执行统计:");
+            for stats in &summary.statement_stats {
+                println!("  - V{} 第 {} 条: {}ms, 读取 {} 行 / {} 字节, 峰值内存 {} 字节",
+                        stats.version, stats.statement_index, stats.duration_ms,
+                        stats.read_rows, stats.read_bytes, stats.memory_usage);
+                if verbose {
+                    println!("    query_id: {}", stats.query_id);
+                }
+            }
         }
+        return;
     }
     
-    Ok(())
+    println!("⚠️  迁移完成，但有失败:");
+    println!("  成功迁移数: {}", summary.successful.len());
+    println!("  失败迁移数: {}", summary.failed.len());
+    println!("  总耗时: {:?}", summary.total_time);
+    
+    // 显示详细的失败信息
+    if !summary.failed.is_empty() {
+        println!("\n❌ 失败的迁移详情:");
+        for (i, failed) in summary.failed.iter().enumerate() {
+            println!("  {}. 版本: {} - {}", i + 1, failed.version, failed.name);
+            println!("     错误: {}", failed.error);
+            println!();
+        }
+    }
+    
+    // 显示成功的迁移信息
+    if !summary.successful.is_empty() {
+        println!("✅ 成功的迁移:");
+        for (i, success) in summary.successful.iter().enumerate() {
+            println!("  {}. 版本: {} - {} (耗时: {}ms)",
+                    i + 1, success.version, success.name, success.execution_time_ms);
+        }
+    }
+    
+    // 在详细模式下，尝试获取更多错误信息
+    if verbose {
+        println!("\n🔍 详细错误诊断:");
+        match migrator.get_failed_migrations().await {
+            Ok(failed_migrations) => {
+                if !failed_migrations.is_empty() {
+                    println!("  数据库中的失败记录:");
+                    for failed in failed_migrations {
+                        println!("    - {}: {} (执行时间: {}ms)",
+                                failed.version, failed.name, failed.execution_time_ms);
+                        if !failed.error_message.is_empty() {
+                            println!("      错误: {}", failed.error_message);
+                        }
+                    }
+                }
+            }
+            Err(e) => println!("  无法获取失败记录: {}", e),
+        }
+    }
+}
+
+/// `info` 中的一行
+#[derive(Serialize)]
+struct MigrationInfo {
+    version: String,
+    name: String,
+    /// applied / failed / pending
+    state: &'static str,
+    attempts: usize,
+    last_run: Option<chrono::DateTime<chrono::Utc>>,
+    error: Option<String>,
+}
+
+/// 按版本列出所有迁移：已应用、失败（最近一次尝试失败）和待执行
+async fn info(migrator: &SimpleMigrator, out: Output) -> i32 {
+    let plan = match migrator.plan().await {
+        Ok(plan) => plan,
+        Err(e) => return out.error("生成迁移计划失败", &e),
+    };
+    let records = match migrator.get_applied_migrations().await {
+        Ok(records) => records,
+        Err(e) => return out.error("获取迁移记录失败", &e),
+    };
+    
+    // 版本 → (名称, 该版本的所有尝试)
+    let mut versions: BTreeMap<String, (String, Vec<&MigrationRecord>)> = BTreeMap::new();
+    for record in &records {
+        versions.entry(record.version.clone())
+            .or_insert_with(|| (record.name.clone(), Vec::new()))
+            .1.push(record);
+    }
+    for migration in plan.pending.iter().chain(&plan.repeatable) {
+        versions.entry(migration.version.clone())
+            .or_insert_with(|| (migration.name.clone(), Vec::new()));
+    }
+    
+    let migrations: Vec<MigrationInfo> = versions.into_iter()
+        .map(|(version, (name, attempts))| {
+            let last = attempts.iter().max_by_key(|record| record.finished_at);
+            let applied = attempts.iter().any(|record| record.success);
+            let pending = plan.pending.iter().chain(&plan.repeatable).any(|m| m.version == version);
+            
+            let state = match last {
+                Some(record) if !record.success && !applied => "failed",
+                _ if pending => "pending",
+                Some(_) => "applied",
+                None => "pending",
+            };
+            MigrationInfo {
+                state,
+                attempts: attempts.len(),
+                last_run: last.map(|record| record.finished_at),
+                error: last.filter(|record| !record.success).map(|record| record.error_message.clone()),
+                version,
+                name,
+            }
+        })
+        .collect();
+    
+    if out.is_json() {
+        out.result(&json!({ "migrations": migrations, "missing_files": plan.missing_files }), |_| {});
+        return 0;
+    }
+    
+    if migrations.is_empty() {
+        println!("ℹ️  没有迁移");
+        return 0;
+    }
+    
+    println!("📋 迁移详情:");
+    println!("  {:<24} {:<10} {:<6} {:<20} 名称", "版本", "状态", "尝试", "最近执行");
+    for migration in &migrations {
+        let state = match migration.state {
+            "failed" => "❌ 失败",
+            "pending" => "⏳ 待执行",
+            _ => "✅ 已应用",
+        };
+        let last_run = migration.last_run
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        
+        println!("  {:<24} {:<10} {:<6} {:<20} {}", migration.version, state, migration.attempts, last_run, migration.name);
+        if let Some(error) = &migration.error {
+            println!("      错误: {}", error.lines().next().unwrap_or_default());
+        }
+    }
+    
+    for version in &plan.missing_files {
+        println!("⚠️  已应用的迁移 {} 在迁移文件中不存在", version);
+    }
+    0
+}
+
+/// 校验已应用迁移的校验和，并报告缺失的迁移文件
+async fn validate(migrator: &SimpleMigrator, out: Output) -> i32 {
+    out.say("🔍 校验已应用的迁移...");
+    let plan = match migrator.plan().await {
+        Ok(plan) => plan,
+        Err(e) => return out.error("校验失败", &e),
+    };
+    
+    let report = json!({
+        "valid": plan.is_executable(),
+        "checksum_mismatches": plan.checksum_mismatches,
+        "missing_files": plan.missing_files,
+        "pending": plan.pending.len(),
+    });
+    out.result(&report, |_| {
+        for version in &plan.missing_files {
+            println!("⚠️  已应用的迁移 {} 在迁移文件中不存在", version);
+        }
+        if plan.is_executable() {
+            println!("✅ 校验通过（{} 个待执行迁移）", plan.pending.len());
+        } else {
+            println!("❌ 校验失败，以下迁移在应用后被修改:");
+            for mismatch in &plan.checksum_mismatches {
+                println!("  - {}", mismatch);
+            }
+        }
+    });
+    
+    if plan.is_executable() { 0 } else { EXIT_VALIDATION_FAILED }
+}
+
+async fn rollback(migrator: &SimpleMigrator, to: Option<&str>, out: Output) -> i32 {
+    match to {
+        Some(version) => {
+            out.say(format!("⏪ 回滚到版本 {}...", version));
+            match migrator.rollback_to(version).await {
+                Ok(versions) => {
+                    out.result(&json!({ "rolled_back": versions }), |_| {
+                        if versions.is_empty() {
+                            println!("✅ 没有需要回滚的迁移");
+                        } else {
+                            println!("✅ 回滚成功: {}", versions.join(", "));
+                        }
+                    });
+                    0
+                }
+                Err(e) => out.error("回滚失败", &e),
+            }
+        }
+        None => {
+            out.say("⏪ 回滚最后一个迁移...");
+            match migrator.rollback_last().await {
+                Ok(()) => {
+                    out.result(&json!({ "success": true }), |_| println!("✅ 回滚成功"));
+                    0
+                }
+                Err(e) => out.error("回滚失败", &e),
+            }
+        }
+    }
+}
+
+/// 在迁移目录中创建下一个版本的迁移文件
+async fn new_migration(migrator: &SimpleMigrator, name: &str, out: Output) -> i32 {
+    match migrator.create_migration(name).await {
+        Ok(path) => {
+            out.result(&json!({ "path": path }), |_| println!("✅ 已创建迁移文件: {}", path.display()));
+            0
+        }
+        Err(e) => out.error("创建迁移文件失败", &e),
+    }
+}
+
+/// 静态检查所有迁移文件，有 error 级别的问题时返回非零退出码
+async fn lint(migrator: &SimpleMigrator, out: Output) -> i32 {
+    out.say("🔍 静态检查迁移 SQL...");
+    match migrator.lint().await {
+        Ok(report) => {
+            out.result(&report, |report| {
+                for issue in &report.issues {
+                    let icon = if issue.severity == Severity::Error { "❌" } else { "⚠️ " };
+                    println!("{} {}", icon, issue);
+                }
+                println!(
+                    "{} 检查了 {} 个迁移文件: {} 个错误, {} 个警告",
+                    if report.has_errors() { "❌" } else { "✅" },
+                    report.files_checked,
+                    report.error_count(),
+                    report.warning_count()
+                );
+            });
+            if report.has_errors() { EXIT_LINT_FAILED } else { 0 }
+        }
+        Err(e) => out.error("静态检查失败", &e),
+    }
+}
+
+/// 导出数据库结构快照并写入文件，返回对象数量
+async fn write_schema(migrator: &SimpleMigrator, path: &str) -> anyhow::Result<usize> {
+    let dump = migrator.dump_schema().await?;
+    std::fs::write(path, dump.to_sql()).with_context(|| format!("Failed to write {}", path))?;
+    Ok(dump.objects.len())
+}
+
+/// `schema dump`：写入快照文件
+async fn dump_schema(migrator: &SimpleMigrator, path: &str, out: Output) -> i32 {
+    match write_schema(migrator, path).await {
+        Ok(objects) => {
+            out.result(&json!({ "path": path, "objects": objects }), |_| {
+                println!("📸 已写入数据库结构快照: {}（{} 个对象）", path, objects);
+            });
+            0
+        }
+        Err(e) => out.error("写入数据库结构快照失败", &e),
+    }
+}
+
+/// 比较数据库的实际结构和结构快照，有差异时返回非零退出码
+async fn drift(migrator: &SimpleMigrator, out: Output) -> i32 {
+    out.say("🔍 比较数据库结构和结构快照...");
+    match migrator.check_drift().await {
+        Ok(report) => {
+            out.result(&report, |report| {
+                if report.has_drift() {
+                    println!("❌ 数据库结构与快照 {} 不一致:", report.snapshot);
+                    for drift in &report.drift {
+                        println!("  - {}", drift);
+                    }
+                } else {
+                    println!("✅ 数据库结构与快照 {} 一致", report.snapshot);
+                }
+            });
+            if report.has_drift() { EXIT_SCHEMA_DRIFT } else { 0 }
+        }
+        Err(e) => out.error("结构漂移检测失败", &e),
+    }
 }